@@ -20,38 +20,76 @@ fn main() {
         .parse()
         .expect("Failed to parse K");
     assert!(k >= 1, "K must be ≥ 1");
-    assert!(k < 64, "K must be < 64");
-    assert!(k % 2 == 1, "K must be odd");
+    assert!(k < 256, "K must be < 256");
     code.push(format!("pub const K: usize = {k};"));
 
     let kmer_bits = 2 * k;
     code.push(format!("pub const KMER_BITS: usize = {kmer_bits};"));
 
-    let canon_bits = kmer_bits - 1;
+    // parity canonicalization drops a bit for odd K, while even K falls back
+    // to the lexicographic minimum and keeps the whole k-mer
+    let canon_bits = if k % 2 == 1 { kmer_bits - 1 } else { kmer_bits };
     code.push(format!("pub const CANON_BITS: usize = {canon_bits};"));
 
     let rot_bits = canon_bits.next_power_of_two().ilog2() as usize;
     code.push(format!("pub const ROT_BITS: usize = {rot_bits};"));
 
-    let kt = select_type(kmer_bits);
+    // ranking lmers needs a spare bit above them
+    let kt = select_type(canon_bits + 1);
     code.push(format!("pub type KT = {kt};"));
 
-    let lmer_bits = kmer_bits - rot_bits;
+    let lmer_bits = canon_bits + 1 - rot_bits;
     code.push(format!("pub const LMER_BITS: usize = {lmer_bits};"));
 
     let lt = select_type(lmer_bits);
     code.push(format!("pub type LT = {lt};"));
 
+    // stranded lmers are necklaces of the full forward k-mer
+    let stranded_rot_bits = kmer_bits.next_power_of_two().ilog2() as usize;
+    code.push(format!(
+        "pub const STRANDED_ROT_BITS: usize = {stranded_rot_bits};"
+    ));
+
+    let stranded_lmer_bits = kmer_bits + 1 - stranded_rot_bits;
+    code.push(format!(
+        "pub const STRANDED_LMER_BITS: usize = {stranded_lmer_bits};"
+    ));
+
+    let slt = select_type(stranded_lmer_bits);
+    code.push(format!("pub type SLT = {slt};"));
+
+    // quaternary lmers are necklaces of the k-mer rotated by whole bases
+    let quat_rot_bits = k.next_power_of_two().ilog2() as usize;
+    code.push(format!("pub const QUAT_ROT_BITS: usize = {quat_rot_bits};"));
+
+    let quat_lmer_bits = kmer_bits + 1 - quat_rot_bits;
+    code.push(format!(
+        "pub const QUAT_LMER_BITS: usize = {quat_lmer_bits};"
+    ));
+
+    let qlt = select_type(quat_lmer_bits);
+    code.push(format!("pub type QLT = {qlt};"));
+
+    // quaternary codes also keep the strand, since lmers are ranked among all necklaces
+    let quat_code_bits = quat_lmer_bits + quat_rot_bits + 1;
+    code.push(format!(
+        "pub const QUAT_CODE_BITS: usize = {quat_code_bits};"
+    ));
+
+    let qct = select_type(quat_code_bits);
+    code.push(format!("pub type QCT = {qct};"));
+
     std::fs::write(out_dir.join("constants.rs"), code.join("\n"))
         .expect("Failed to write const file");
 }
 
-fn select_type(n_bits: usize) -> &'static str {
+fn select_type(n_bits: usize) -> String {
     match n_bits.next_power_of_two() {
-        8 => "u8",
-        16 => "u16",
-        32 => "u32",
-        64 => "u64",
-        _ => "u128",
+        0..=8 => "u8".into(),
+        16 => "u16".into(),
+        32 => "u32".into(),
+        64 => "u64".into(),
+        128 => "u128".into(),
+        _ => format!("crate::wide::WideUint<{}>", n_bits.div_ceil(64)),
     }
 }
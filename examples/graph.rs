@@ -1,22 +1,24 @@
 use lmer::constants::{CANON_BITS, K, KT};
 use lmer::lyndon::{bin_rot_right, necklace};
+use lmer::utils::low_mask;
+use num_traits::{NumCast, One, Zero};
 use rand::Rng;
 use std::collections::HashSet;
 
-const MASK: KT = (1 << CANON_BITS) - 1;
-
 use std::fs::File;
 use std::io::Write;
 
 fn main() {
+    let mask: KT = low_mask(CANON_BITS);
     let mut rng = rand::thread_rng();
-    let mut kmer = 0u64;
+    let mut random_bit = || -> KT { NumCast::from(rng.gen_range(0..2)).unwrap() };
+    let mut kmer = KT::zero();
     for _ in 0..(CANON_BITS - 1) {
-        kmer = (kmer << 1) | rng.gen_range(0..2);
+        kmer = (kmer << 1) | random_bit();
     }
     let mut set_necks = HashSet::new();
     for _ in 0..1000 {
-        kmer = ((kmer << 1) | rng.gen_range(0..2)) & MASK;
+        kmer = ((kmer << 1) | random_bit()) & mask;
         set_necks.insert(necklace::<K, _>(kmer));
     }
     println!("{} necklaces", set_necks.len());
@@ -27,7 +29,7 @@ fn main() {
     for &neck in set_necks.iter() {
         let mut rot = neck;
         for _ in 0..CANON_BITS {
-            let s = (rot >> 1) | ((1 - (rot & 1)) << (CANON_BITS - 1));
+            let s = (rot >> 1) | ((KT::one() ^ (rot & KT::one())) << (CANON_BITS - 1));
             let ns = necklace::<K, _>(s);
             if set_necks.contains(&ns) {
                 writeln!(dot, "{:b} -> {:b}", neck, ns).unwrap();
@@ -0,0 +1,42 @@
+use lmer::constants::{CANON_BITS, K, KT, LMER_BITS, QUAT_CODE_BITS, QUAT_LMER_BITS};
+use lmer::kmer::RawKmer;
+use lmer::lyndon::Lyndon;
+use lmer::quaternary::{num_quaternary_necklaces, QuaternaryLyndon};
+use lmer::rank::num_necklaces;
+use lmer::utils::*;
+use std::collections::BTreeMap;
+
+const N: usize = 1_000_000;
+
+fn main() {
+    println!("K={K}, {N} k-mers");
+    let kmers = random_kmers::<K, KT, RawKmer<K, KT>>(N);
+    let mut binary = BTreeMap::<_, usize>::new();
+    let mut quaternary = BTreeMap::<_, usize>::new();
+    for &kmer in kmers.iter() {
+        *binary.entry(kmer.lmer()).or_default() += 1;
+        *quaternary.entry(kmer.quaternary_lmer()).or_default() += 1;
+    }
+
+    println!("Binary lmers: {LMER_BITS} bits");
+    if CANON_BITS < 128 {
+        println!("universe of {} necklaces", num_necklaces(CANON_BITS));
+    }
+    print_stats(&binary);
+
+    println!("Quaternary lmers: {QUAT_LMER_BITS} bits, codes of {QUAT_CODE_BITS} bits");
+    if K < 64 {
+        println!("universe of {} necklaces", num_quaternary_necklaces(K));
+    }
+    print_stats(&quaternary);
+}
+
+fn print_stats<T>(buckets: &BTreeMap<T, usize>) {
+    let max = buckets.values().max().unwrap();
+    println!(
+        "{} buckets, {:.3} k-mers/bucket, at most {max}",
+        buckets.len(),
+        N as f64 / buckets.len() as f64
+    );
+    println!();
+}
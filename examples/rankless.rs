@@ -1,6 +1,6 @@
 use lmer::kmer::RawKmer;
 use lmer::lyndon::Lyndon;
-use lmer::rank::Ranker;
+use lmer::rank::{num_necklaces, Ranker};
 use lmer::utils::*;
 use num_format::{Locale, ToFormattedString};
 use roaring::RoaringBitmap;
@@ -46,31 +46,14 @@ fn bench_rankless_long<const N: usize, const K: usize, const B: usize>() {
     bench_ef::<N, K, B, true>(&kmers);
 }
 
-fn gcd(a: usize, b: usize) -> usize {
-    let mut a = a;
-    let mut b = b;
-    while b != 0 {
-        (a, b) = (b, a % b);
-    }
-    a
-}
-
-fn phi(n: usize) -> usize {
-    (1..=n).filter(|&i| gcd(n, i) == 1).count()
-}
-
-fn num_necklaces(n: usize) -> usize {
-    (1..=n)
-        .filter(|d| n % d == 0)
-        .map(|d| phi(n / d) << d)
-        .sum::<usize>()
-        / n
-}
-
 fn bench_roaring<const N: usize, const K: usize, const B: usize, const RANK: bool>(
     kmers: &Vec<RawKmer<K, T>>,
 ) {
-    let u = if RANK { num_necklaces(B) } else { 1 << B };
+    let u = if RANK {
+        num_necklaces(B) as usize
+    } else {
+        1 << B
+    };
     let mut roaring = RoaringBitmap::new();
     let ranker = Ranker::<B, T>::new();
 
@@ -117,7 +100,11 @@ fn bench_roaring<const N: usize, const K: usize, const B: usize, const RANK: boo
 fn bench_ef<const N: usize, const K: usize, const B: usize, const RANK: bool>(
     kmers: &Vec<RawKmer<K, T>>,
 ) {
-    let u = if RANK { num_necklaces(B) } else { 1 << B };
+    let u = if RANK {
+        num_necklaces(B) as usize
+    } else {
+        1 << B
+    };
     let mut efb = EliasFanoBuilder::new(u, N).expect("Failed to create Elias-Fano Builder");
     let ranker = Ranker::<B, T>::new();
 
@@ -1,8 +1,9 @@
-use lmer::constants::{CANON_BITS, K, KT, LT, ROT_BITS};
+use lmer::constants::{CANON_BITS, K, KT, LT};
 use lmer::kmer::{Kmer, RawKmer};
 use lmer::lyndon::Lyndon;
 use lmer::rank::Ranker;
 use lmer::utils::*;
+use num_traits::NumCast;
 use std::fs::File;
 use std::io::Write;
 
@@ -17,14 +18,13 @@ fn main() {
     let kmers = random_kmers::<K, KT, RawKmer<K, KT>>(1_000_000);
     let ranker = Ranker::<CANON_BITS, KT>::new();
     for kmer in kmers {
-        let kmer_cp = kmer.canonical().to_int() >> 1;
-        let (lmer, idx) = kmer.lmer_index();
-        let rank = ranker.rank(lmer);
-        let rank_idx = (rank << ROT_BITS) | idx as KT;
+        let kmer_cp = kmer.canonical().to_int() >> (2 * K - CANON_BITS);
+        let rank = ranker.rank(kmer.lmer());
+        let code = kmer.dense_code(&ranker);
 
         set_kmers_cp.insert(kmer_cp);
-        set_lmers_idx.insert(rank_idx);
-        set_ranks.insert(rank as LT);
+        set_lmers_idx.insert(code);
+        set_ranks.insert(<LT as NumCast>::from(rank).unwrap());
     }
 
     let mut out_kmers = File::create(format!("sorted_kmers_{}.txt", K)).unwrap();
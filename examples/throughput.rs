@@ -1,7 +1,7 @@
 use lmer::constants::KT;
 use lmer::kmer::RawKmer;
 use lmer::lyndon::Lyndon;
-use lmer::rank::Ranker;
+use lmer::rank::{Ranker, TableRanker};
 use lmer::utils::*;
 use std::time::Instant;
 
@@ -25,7 +25,6 @@ fn main() {
 fn bench_throughput<const K: usize, const B: usize>() {
     let kmers = random_kmers::<K, KT, RawKmer<K, KT>>(N);
     let mut lmers = Vec::with_capacity(N);
-    let mut ranks = Vec::with_capacity(N);
     println!("Throughput for k={}:", K);
 
     let now = Instant::now();
@@ -37,9 +36,20 @@ fn bench_throughput<const K: usize, const B: usize>() {
 
     let now = Instant::now();
     let ranker = Ranker::<B, KT>::new();
-    for lmer in lmers {
-        ranks.push(ranker.rank(lmer));
-    }
+    let ranks = ranker.rank_batch(&lmers);
+    assert_eq!(ranks.len(), N);
     let elapsed = now.elapsed().as_nanos();
     println!("{} ns/lmer to compute rank", elapsed / N as u128);
+
+    if B <= TableRanker::<B, KT>::MAX_N {
+        let table = TableRanker::<B, KT>::new();
+        let now = Instant::now();
+        let table_ranks = table.rank_batch(&lmers);
+        let elapsed = now.elapsed().as_nanos();
+        assert_eq!(table_ranks, ranks);
+        println!(
+            "{} ns/lmer to compute rank with a table",
+            elapsed / N as u128
+        );
+    }
 }
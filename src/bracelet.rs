@@ -0,0 +1,233 @@
+// Bracelets identify words up to rotation and reversal, so that the lmer of a
+// k-mer and the lmer of its mirror image share the same bracelet.
+//
+// `BraceletRanker` stores the sorted list of bracelets, so it only ranks lengths
+// up to `BraceletRanker::MAX_N` = 25, i.e. k-mers up to K = 13. For larger K,
+// `num_bracelets` measures the bits saved over necklaces without ranking them.
+
+use crate::canonical::{canon_bits, Auto, Canonicalizer};
+use crate::kmer::{Base, Kmer};
+use crate::lyndon::{fast_necklace_index_n, fast_necklace_n, necklaces_n, period_n};
+use crate::rank::{num_necklaces, RankError};
+use crate::utils::low_mask;
+use num_traits::int::PrimInt;
+
+/// Reverse the bits of the `n`-bit word `x`
+#[inline]
+pub fn bin_reverse_n<T: PrimInt>(x: T, n: usize) -> T {
+    x.reverse_bits() >> (T::zero().count_zeros() as usize - n)
+}
+
+/// Smallest word among the rotations of `x` and of its reversal, over `n` bits
+pub fn bracelet_n<T: Base>(x: T, n: usize) -> T {
+    fast_necklace_n(x, n).min(fast_necklace_n(bin_reverse_n(x, n), n))
+}
+
+/// Same as `bracelet_n` with the number of right rotations to reach it,
+/// and whether the word was reversed first
+pub fn bracelet_index_n<T: Base>(x: T, n: usize) -> (T, usize, bool) {
+    let (neck, idx) = fast_necklace_index_n(x, n);
+    let (rev_neck, rev_idx) = fast_necklace_index_n(bin_reverse_n(x, n), n);
+    if neck <= rev_neck {
+        (neck, idx, false)
+    } else {
+        (rev_neck, rev_idx, true)
+    }
+}
+
+/// Recover the word from the output of `bracelet_index_n`
+pub fn from_bracelet_index_n<T: Base>(bracelet: T, idx: usize, flip: bool, n: usize) -> T {
+    let idx = idx % n;
+    let x = if idx == 0 {
+        bracelet
+    } else {
+        ((bracelet << idx) & low_mask(n)) | (bracelet >> (n - idx))
+    };
+    if flip {
+        bin_reverse_n(x, n)
+    } else {
+        x
+    }
+}
+
+/// Check that the `n`-bit word `x` is a bracelet
+#[inline]
+pub fn is_bracelet_n<T: Base>(x: T, n: usize) -> bool {
+    fast_necklace_n(x, n) == x && x <= fast_necklace_n(bin_reverse_n(x, n), n)
+}
+
+/// Number of binary bracelets of length n, for 1 ≤ n ≤ 127
+pub fn num_bracelets(n: usize) -> u128 {
+    let necklaces = num_necklaces(n);
+    if n % 2 == 1 {
+        // N/2 + 2^((n+1)/2)/2
+        (necklaces + (1 << (n / 2 + 1))) / 2
+    } else {
+        // N/2 + 3 * 2^(n/2)/4
+        (2 * necklaces + 3 * (1 << (n / 2))) / 4
+    }
+}
+
+/// Bracelet lmers, grouping the lmers of canonical k-mers up to reversal
+pub trait BraceletLyndon<const K: usize, T: Base>: Kmer<K, T> {
+    fn bracelet_lmer(self) -> T {
+        bracelet_n(Auto::to_word(self.canonical().to_int(), K), canon_bits(K))
+    }
+    /// Same as `bracelet_lmer` with the rotation index and the flip bit
+    fn bracelet_lmer_index(self) -> (T, usize, bool) {
+        bracelet_index_n(Auto::to_word(self.canonical().to_int(), K), canon_bits(K))
+    }
+    /// Recover the canonical k-mer from the output of `bracelet_lmer_index`
+    fn from_bracelet_lmer_index(bracelet: T, idx: usize, flip: bool) -> Self {
+        let x = from_bracelet_index_n(bracelet, idx, flip, canon_bits(K));
+        Self::from_int(Auto::from_word(x, K))
+    }
+}
+
+impl<const K: usize, T: Base, KT: Kmer<K, T>> BraceletLyndon<K, T> for KT {}
+
+/// Ranker for bracelets of length N ≤ 25, backed by the sorted list of bracelets
+/// which takes `num_bracelets(N)` words
+pub struct BraceletRanker<const N: usize, T> {
+    bracelets: Vec<T>,
+}
+
+impl<const N: usize, T: Base> Default for BraceletRanker<N, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, T: Base> BraceletRanker<N, T> {
+    pub const MAX_N: usize = 25;
+
+    /// Panics if N > MAX_N or if `T` has less than N bits
+    pub fn new() -> Self {
+        match Self::try_new() {
+            Ok(ranker) => ranker,
+            Err(_) => panic!("N must be ≤ {} and fit in the integer type", Self::MAX_N),
+        }
+    }
+
+    pub fn try_new() -> Result<Self, RankError> {
+        if N > Self::MAX_N || N > T::zero().count_zeros() as usize {
+            return Err(RankError::Overflow);
+        }
+        let bracelets = necklaces_n::<T>(N)
+            .filter(|&x| x <= fast_necklace_n(bin_reverse_n(x, N), N))
+            .collect();
+        Ok(Self { bracelets })
+    }
+
+    /// Number of bracelets of length N
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bracelets.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bracelets.is_empty()
+    }
+
+    /// Rank of w, i.e. number of bracelets <= w minus one
+    #[inline]
+    pub fn rank(&self, w: T) -> T {
+        T::from(self.bracelets.partition_point(|&x| x <= w)).unwrap() - T::one()
+    }
+
+    /// Bracelet of rank r
+    #[inline]
+    pub fn unrank(&self, r: T) -> T {
+        self.bracelets[r.to_usize().unwrap()]
+    }
+
+    /// Number of distinct words reached by rotations and reversal of the bracelet
+    pub fn orbit_size(&self, bracelet: T) -> usize {
+        let p = period_n(bracelet, N);
+        if fast_necklace_n(bin_reverse_n(bracelet, N), N) == bracelet {
+            p
+        } else {
+            2 * p
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::RawKmer;
+    use crate::lyndon::{necklace_n, Lyndon};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_bracelet() {
+        const N: usize = 13;
+        let mut bracelets = BTreeSet::new();
+        for x in 0u32..(1 << N) {
+            let (bracelet, idx, flip) = bracelet_index_n(x, N);
+            assert!(is_bracelet_n(bracelet, N));
+            assert_eq!(bracelet, bracelet_n(bin_reverse_n(x, N), N));
+            assert_eq!(
+                bracelet,
+                necklace_n(x, N).min(necklace_n(bin_reverse_n(x, N), N))
+            );
+            assert_eq!(from_bracelet_index_n(bracelet, idx, flip, N), x);
+            bracelets.insert(bracelet);
+        }
+        assert_eq!(bracelets.len() as u128, num_bracelets(N));
+    }
+
+    #[test]
+    fn test_num_bracelets() {
+        // OEIS A000029
+        assert_eq!(
+            (1..=12).map(num_bracelets).collect::<Vec<_>>(),
+            [2, 3, 4, 6, 8, 13, 18, 30, 46, 78, 126, 224]
+        );
+        assert_eq!(
+            BraceletRanker::<20, u32>::new().len() as u128,
+            num_bracelets(20)
+        );
+        assert_eq!(
+            BraceletRanker::<21, u32>::new().len() as u128,
+            num_bracelets(21)
+        );
+        assert!(BraceletRanker::<26, u32>::try_new().is_err());
+        assert!(BraceletRanker::<20, u16>::try_new().is_err());
+    }
+
+    #[test]
+    fn test_bracelet_rank() {
+        const N: usize = 15;
+        let ranker = BraceletRanker::<N, u32>::new();
+        let mut r = 0;
+        for x in 0..(1 << N) {
+            if is_bracelet_n(x, N) {
+                assert_eq!(ranker.rank(x), r);
+                assert_eq!(ranker.unrank(r), x);
+                r += 1;
+            }
+        }
+        let orbits: usize = (0..ranker.len() as u32)
+            .map(|r| ranker.orbit_size(ranker.unrank(r)))
+            .sum();
+        assert_eq!(orbits, 1 << N);
+    }
+
+    #[test]
+    fn test_bracelet_lmer() {
+        const K: usize = 9;
+        type KT = RawKmer<K, u32>;
+        for i in (0..(1 << (2 * K))).step_by(5) {
+            let kmer = KT::from_int(i);
+            let (bracelet, idx, flip) = kmer.bracelet_lmer_index();
+            assert_eq!(bracelet, kmer.bracelet_lmer());
+            assert!(bracelet <= kmer.lmer());
+            assert_eq!(
+                KT::from_bracelet_lmer_index(bracelet, idx, flip),
+                kmer.canonical()
+            );
+        }
+    }
+}
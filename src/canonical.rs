@@ -0,0 +1,193 @@
+// Strategies choosing which of a k-mer and its reverse complement is canonical.
+// The lmer of a canonical k-mer is the necklace of a word derived from it:
+// parity drops the last bit since it can be recovered, other strategies keep
+// all 2K bits.
+// Parity only pairs a k-mer with its reverse complement when K is odd: for even
+// K both have the same parity, and some k-mers are their own reverse complement.
+// `Auto` therefore falls back to `LexMin` for even K, which keeps palindromes as is.
+
+use crate::kmer::{Base, Kmer};
+use crate::utils::low_mask;
+
+pub trait Canonicalizer {
+    /// Check that the k-mer is the canonical one among itself and its reverse complement
+    fn is_canonical<const K: usize, T: Base, KT: Kmer<K, T>>(kmer: KT) -> bool;
+    /// Length in bits of the word whose necklace is the lmer
+    #[inline]
+    fn word_bits(k: usize) -> usize {
+        2 * k
+    }
+    /// Word of a canonical k-mer
+    #[inline]
+    fn to_word<T: Base>(canonical: T, _k: usize) -> T {
+        canonical
+    }
+    /// Recover the canonical k-mer from the output of `to_word`
+    #[inline]
+    fn from_word<T: Base>(word: T, _k: usize) -> T {
+        word
+    }
+}
+
+/// Length of the lmers of k-mers under the `Auto` strategy, i.e. 2K-1 for odd K and 2K for even K
+#[inline]
+pub const fn canon_bits(k: usize) -> usize {
+    2 * k - (k % 2)
+}
+
+/// Canonical k-mers have an even number of set bits, which requires odd K
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Parity;
+
+impl Canonicalizer for Parity {
+    #[inline]
+    fn is_canonical<const K: usize, T: Base, KT: Kmer<K, T>>(kmer: KT) -> bool {
+        kmer.to_int().count_ones() % 2 == 0
+    }
+    #[inline]
+    fn word_bits(k: usize) -> usize {
+        2 * k - 1
+    }
+    #[inline]
+    fn to_word<T: Base>(canonical: T, _k: usize) -> T {
+        canonical >> 1
+    }
+    #[inline]
+    fn from_word<T: Base>(word: T, _k: usize) -> T {
+        // the dropped bit restores the even parity of canonical k-mers
+        let parity = if word.count_ones() % 2 == 1 {
+            T::one()
+        } else {
+            T::zero()
+        };
+        (word << 1) | parity
+    }
+}
+
+/// Canonical k-mers are the smallest of themselves and their reverse complement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LexMin;
+
+impl Canonicalizer for LexMin {
+    #[inline]
+    fn is_canonical<const K: usize, T: Base, KT: Kmer<K, T>>(kmer: KT) -> bool {
+        kmer <= kmer.rev_comp()
+    }
+}
+
+/// Canonical k-mers have the smallest hash among themselves and their reverse
+/// complement, with ties broken lexicographically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HashMin;
+
+impl Canonicalizer for HashMin {
+    #[inline]
+    fn is_canonical<const K: usize, T: Base, KT: Kmer<K, T>>(kmer: KT) -> bool {
+        let rc = kmer.rev_comp();
+        (hash_int(kmer.to_int()), kmer) <= (hash_int(rc.to_int()), rc)
+    }
+}
+
+/// `Parity` for odd K and `LexMin` for even K, used by `Kmer::canonical` and `Lyndon::lmer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Auto;
+
+impl Canonicalizer for Auto {
+    #[inline]
+    fn is_canonical<const K: usize, T: Base, KT: Kmer<K, T>>(kmer: KT) -> bool {
+        if K % 2 == 1 {
+            Parity::is_canonical(kmer)
+        } else {
+            LexMin::is_canonical(kmer)
+        }
+    }
+    #[inline]
+    fn word_bits(k: usize) -> usize {
+        canon_bits(k)
+    }
+    #[inline]
+    fn to_word<T: Base>(canonical: T, k: usize) -> T {
+        if k % 2 == 1 {
+            Parity::to_word(canonical, k)
+        } else {
+            canonical
+        }
+    }
+    #[inline]
+    fn from_word<T: Base>(word: T, k: usize) -> T {
+        if k % 2 == 1 {
+            Parity::from_word(word, k)
+        } else {
+            word
+        }
+    }
+}
+
+/// Finalizer of MurmurHash3
+#[inline]
+fn fmix64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^ (h >> 33)
+}
+
+/// Deterministic hash of an integer, mixing it by chunks of 64 bits
+pub fn hash_int<T: Base>(x: T) -> u64 {
+    let bits = T::zero().count_zeros() as usize;
+    if bits <= 64 {
+        return fmix64(x.to_u64().unwrap());
+    }
+    let mut x = x;
+    let mut h = 0;
+    for _ in 0..bits.div_ceil(64) {
+        h = fmix64(h ^ (x & low_mask(64)).to_u64().unwrap());
+        x = x >> 64;
+    }
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::{RawKmer, RevComp};
+    use crate::lyndon::Lyndon;
+
+    fn check_strategy<C: Canonicalizer>() {
+        const K: usize = 7;
+        type KT = RawKmer<K, u16>;
+        for i in 0..(1 << (2 * K)) {
+            let kmer = KT::from_int(i);
+            let canon = kmer.canonical_with::<C>();
+            assert!(canon == kmer || canon == kmer.rev_comp());
+            assert!(canon.is_canonical_with::<C>());
+            assert_eq!(kmer.rev_comp().canonical_with::<C>(), canon);
+            let (lmer, idx) = kmer.lmer_index_with::<C>();
+            assert_eq!(lmer, kmer.lmer_with::<C>());
+            assert_eq!(lmer, kmer.rev_comp().lmer_with::<C>());
+            assert_eq!(KT::from_lmer_index_with::<C>(lmer, idx), canon);
+        }
+    }
+
+    #[test]
+    fn test_strategies() {
+        check_strategy::<Parity>();
+        check_strategy::<LexMin>();
+        check_strategy::<HashMin>();
+        check_strategy::<Auto>();
+    }
+
+    #[test]
+    fn test_parity_default() {
+        const K: usize = 9;
+        type KT = RawKmer<K, u32>;
+        for i in (0..(1 << (2 * K))).step_by(7) {
+            let kmer = KT::from_int(i);
+            assert_eq!(kmer.canonical_with::<Parity>(), kmer.canonical());
+            assert_eq!(kmer.lmer_index_with::<Parity>(), kmer.lmer_index());
+            let lexmin = kmer.canonical_with::<LexMin>();
+            assert_eq!(lexmin, kmer.min(kmer.rev_comp()));
+        }
+    }
+}
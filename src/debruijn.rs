@@ -0,0 +1,103 @@
+use crate::kmer::Base;
+use crate::lyndon::{necklaces_n, period_n};
+use crate::quaternary::next_base_prenecklace_n;
+use seq_io::fasta;
+use std::io::{self, Write};
+
+/// Largest number of elements reserved up front, larger sequences grow on demand
+const MAX_RESERVED: usize = 1 << 24;
+
+/// Binary de Bruijn sequence of order `n`, made of 2^n bits read cyclically.
+/// It concatenates the Lyndon words whose length divides `n` in lex order,
+/// i.e. the aperiodic prefix of each necklace.
+pub fn binary_de_bruijn(n: usize) -> Vec<u8> {
+    assert!((1..64).contains(&n), "n must be in 1..64");
+    let mut seq = Vec::with_capacity(MAX_RESERVED.min(1 << n));
+    for x in necklaces_n::<u64>(n) {
+        let p = period_n(x, n);
+        seq.extend((0..p).map(|i| ((x >> (n - 1 - i)) & 1) as u8));
+    }
+    seq
+}
+
+/// DNA de Bruijn sequence of order `k`, made of 4^k bases read cyclically.
+/// Same construction as `binary_de_bruijn` with the prenecklaces over bases.
+pub fn dna_de_bruijn<T: Base>(k: usize) -> Vec<T> {
+    assert!((1..32).contains(&k), "k must be in 1..32");
+    let mut seq = Vec::with_capacity(MAX_RESERVED.min(1 << (2 * k)));
+    let mut next = Some((0u64, 1));
+    while let Some((w, p)) = next {
+        if k.is_multiple_of(p) {
+            seq.extend((0..p).map(|i| T::from((w >> (2 * (k - 1 - i))) & 0b11).unwrap()));
+        }
+        next = next_base_prenecklace_n(w, k);
+    }
+    seq
+}
+
+/// Nucleotides of the DNA de Bruijn sequence of order `k`, unrolled so that
+/// each k-mer appears exactly once when reading the 4^k + k - 1 nucleotides
+pub fn dna_de_bruijn_nucs(k: usize) -> Vec<u8> {
+    let mut seq = dna_de_bruijn::<u8>(k);
+    seq.extend_from_within(..(k - 1));
+    seq.into_iter().map(|base| base.to_nuc()).collect()
+}
+
+/// Write the output of `dna_de_bruijn_nucs` as a FASTA record
+pub fn write_dna_de_bruijn<W: Write>(writer: W, k: usize) -> io::Result<()> {
+    let head = format!("de_bruijn_k{k}");
+    fasta::write_wrap(writer, head.as_str(), &dna_de_bruijn_nucs(k), 80)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::{Kmer, RawKmer};
+    use crate::lyndon::Lyndon;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_binary_de_bruijn() {
+        assert_eq!(binary_de_bruijn(3), [0, 0, 0, 1, 0, 1, 1, 1]);
+        for n in 1..=14 {
+            let seq = binary_de_bruijn(n);
+            assert_eq!(seq.len(), 1 << n);
+            let words: HashSet<_> = (0..seq.len())
+                .map(|i| (0..n).fold(0u32, |x, j| (x << 1) | seq[(i + j) % seq.len()] as u32))
+                .collect();
+            assert_eq!(words.len(), 1 << n);
+        }
+    }
+
+    #[test]
+    fn test_dna_de_bruijn() {
+        const K: usize = 7;
+        let nucs = dna_de_bruijn_nucs(K);
+        assert_eq!(nucs.len(), (1 << (2 * K)) + K - 1);
+        let kmers: HashSet<_> = RawKmer::<K, u16>::iter_from_nucs(nucs.iter())
+            .map(|kmer| kmer.to_int())
+            .collect();
+        assert_eq!(kmers.len(), 1 << (2 * K));
+        let lmers: Vec<_> = RawKmer::<K, u16>::iter_from_nucs(nucs.iter())
+            .map(|kmer| kmer.lmer_index())
+            .collect();
+        let lmers_iter: Vec<_> =
+            RawKmer::<K, u16>::iter_lmer_index_from_nucs::<{ 2 * K - 1 }, 5, _>(nucs.iter())
+                .collect();
+        assert_eq!(lmers_iter, lmers);
+        assert_eq!(dna_de_bruijn_nucs(1), b"ACTG");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dna_de_bruijn_too_large() {
+        dna_de_bruijn::<u8>(32);
+    }
+
+    #[test]
+    fn test_write_fasta() {
+        let mut out = Vec::new();
+        write_dna_de_bruijn(&mut out, 2).unwrap();
+        assert_eq!(out, b">de_bruijn_k2\nAACATAGCCTCGTTGGA\n");
+    }
+}
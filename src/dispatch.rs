@@ -0,0 +1,106 @@
+use crate::canonical::canon_bits;
+use crate::kmer::{Base, Kmer, RawKmer};
+
+/// Code path generic over K, to be instantiated for every K by `dispatch_k`.
+/// `N = canon_bits(K)` is the length of the lmers, and `T` the smallest integer type
+/// holding an lmer with a spare bit, so that `RawKmer<K, T>`, `Lyndon` and
+/// `Ranker<N, T>` can be used.
+pub trait KmerFn {
+    type Output;
+    fn call<const K: usize, const N: usize, T: Base>(self) -> Self::Output
+    where
+        RawKmer<K, T>: Kmer<K, T>;
+}
+
+macro_rules! dispatch {
+($k:expr, $f:expr; $($T:ty: $($K:literal)+);+) => {
+    match $k {
+        $($($K => $f.call::<$K, { canon_bits($K) }, $T>(),)+)+
+        k => panic!("K must be in 1..64, got {k}"),
+    }
+}}
+
+/// Run `f` with the compile-time K equal to the runtime value `k`,
+/// which allows choosing K from the command line without rebuilding.
+pub fn dispatch_k<F: KmerFn>(k: usize, f: F) -> F::Output {
+    dispatch!(k, f;
+        u8: 1 2 3;
+        u16: 4 5 6 7;
+        u32: 8 9 10 11 12 13 14 15;
+        u64: 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31;
+        u128: 32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47
+              48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamic::DynKmer;
+    use crate::lyndon::Lyndon;
+    use crate::rank::Ranker;
+
+    const NUCS: &[u8] = b"CATAATCCAGCTTGACCAGTACGATTACAGAGCATGCATCCAGGATTACAGAGGACGTACGTACCA";
+
+    struct Lmers<'a>(&'a [u8]);
+
+    impl KmerFn for Lmers<'_> {
+        type Output = Vec<u128>;
+        fn call<const K: usize, const N: usize, T: Base>(self) -> Vec<u128>
+        where
+            RawKmer<K, T>: Kmer<K, T>,
+        {
+            assert_eq!(N, canon_bits(K));
+            RawKmer::<K, T>::iter_from_nucs(self.0.iter())
+                .map(|kmer| kmer.lmer().to_u128().unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_dispatch_lmers() {
+        for k in 1..64 {
+            let params = DynKmer::<u128>::new(k);
+            let lmers: Vec<_> = params
+                .iter_from_nucs(NUCS.iter())
+                .map(|kmer| params.lmer(kmer))
+                .collect();
+            assert_eq!(dispatch_k(k, Lmers(NUCS)), lmers);
+        }
+    }
+
+    struct FirstRank;
+
+    impl KmerFn for FirstRank {
+        type Output = u128;
+        fn call<const K: usize, const N: usize, T: Base>(self) -> u128
+        where
+            RawKmer<K, T>: Kmer<K, T>,
+        {
+            let lmer = RawKmer::<K, T>::from_nucs(NUCS).lmer();
+            Ranker::<N, T>::new().rank(lmer).to_u128().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_dispatch_rank() {
+        assert_eq!(dispatch_k(9, FirstRank), {
+            let lmer = RawKmer::<9, u32>::from_nucs(NUCS).lmer();
+            Ranker::<17, u32>::new().rank(lmer) as u128
+        });
+    }
+
+    #[test]
+    fn test_dispatch_even() {
+        assert_eq!(dispatch_k(32, FirstRank), {
+            let lmer = RawKmer::<32, u128>::from_nucs(NUCS).lmer();
+            Ranker::<64, u128>::new().rank(lmer)
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dispatch_too_large() {
+        dispatch_k(64, FirstRank);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::canonical::{canon_bits, Auto, Canonicalizer};
+use crate::kmer::{Base, Strand};
+use crate::lyndon::{
+    bin_rot_left_n, canonical_index_n, fast_necklace_index_n, fast_necklace_n, is_lyndon_n,
+    period_n,
+};
+use crate::utils::low_mask;
+use core::iter::FilterMap;
+
+/// Runtime counterpart of `Kmer` and `Lyndon`, where K is chosen at construction.
+/// K-mers and lmers are plain integers of type `T`, and the constants generated
+/// by `build.rs` are computed in `new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynKmer<T: Base> {
+    k: usize,
+    kmer_bits: usize,
+    canon_bits: usize,
+    rot_bits: usize,
+    lmer_bits: usize,
+    stranded_rot_bits: usize,
+    stranded_lmer_bits: usize,
+    mask: T,
+    rc_masks: [T; 3],
+}
+
+impl<T: Base> DynKmer<T> {
+    pub fn new(k: usize) -> Self {
+        let bits = T::zero().count_zeros() as usize;
+        assert!(k >= 1, "K must be ≥ 1");
+        assert!(2 * k <= bits, "K must be ≤ {}", bits / 2);
+        let kmer_bits = 2 * k;
+        let canon_bits = canon_bits(k);
+        let rot_bits = canon_bits.next_power_of_two().ilog2() as usize;
+        let lmer_bits = canon_bits + 1 - rot_bits;
+        let stranded_rot_bits = kmer_bits.next_power_of_two().ilog2() as usize;
+        let stranded_lmer_bits = kmer_bits + 1 - stranded_rot_bits;
+        // 0x0F.., 0x33.. and 0x55.. patterns
+        let rc_masks = [17, 5, 3].map(|d| T::max_value() / T::from(d).unwrap());
+        Self {
+            k,
+            kmer_bits,
+            canon_bits,
+            rot_bits,
+            lmer_bits,
+            stranded_rot_bits,
+            stranded_lmer_bits,
+            mask: low_mask(kmer_bits),
+            rc_masks,
+        }
+    }
+
+    #[inline]
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    #[inline]
+    pub fn kmer_bits(&self) -> usize {
+        self.kmer_bits
+    }
+
+    #[inline]
+    pub fn canon_bits(&self) -> usize {
+        self.canon_bits
+    }
+
+    #[inline]
+    pub fn rot_bits(&self) -> usize {
+        self.rot_bits
+    }
+
+    #[inline]
+    pub fn lmer_bits(&self) -> usize {
+        self.lmer_bits
+    }
+
+    #[inline]
+    pub fn stranded_rot_bits(&self) -> usize {
+        self.stranded_rot_bits
+    }
+
+    #[inline]
+    pub fn stranded_lmer_bits(&self) -> usize {
+        self.stranded_lmer_bits
+    }
+
+    #[inline]
+    pub fn mask(&self) -> T {
+        self.mask
+    }
+
+    #[inline]
+    pub fn extend(&self, kmer: T, base: T) -> T {
+        (kmer << 2) | base
+    }
+
+    #[inline]
+    pub fn append(&self, kmer: T, base: T) -> T {
+        ((kmer << 2) | base) & self.mask
+    }
+
+    #[inline]
+    pub fn prepend(&self, kmer: T, base: T) -> T {
+        (kmer >> 2) | (base << (2 * (self.k - 1)))
+    }
+
+    pub fn rev_comp(&self, kmer: T) -> T {
+        let [m4, m2, m1] = self.rc_masks;
+        let mut res = kmer.swap_bytes();
+        res = ((res >> 4) & m4) | ((res & m4) << 4);
+        res = ((res >> 2) & m2) | ((res & m2) << 2);
+        res = res ^ !m1;
+        res >> (T::zero().count_zeros() as usize - self.kmer_bits)
+    }
+
+    /// Same as `Kmer::is_canonical`, i.e. parity for odd K and lexicographic order for even K
+    #[inline]
+    pub fn is_canonical(&self, kmer: T) -> bool {
+        if self.k % 2 == 1 {
+            kmer.count_ones().is_multiple_of(2)
+        } else {
+            kmer <= self.rev_comp(kmer)
+        }
+    }
+
+    #[inline]
+    pub fn canonical(&self, kmer: T) -> T {
+        if self.is_canonical(kmer) {
+            kmer
+        } else {
+            self.rev_comp(kmer)
+        }
+    }
+
+    /// Same as `canonical`, with the strand of the k-mer relative to its canonical form
+    #[inline]
+    pub fn canonical_with_strand(&self, kmer: T) -> (T, Strand) {
+        if self.is_canonical(kmer) {
+            (kmer, Strand::Forward)
+        } else {
+            (self.rev_comp(kmer), Strand::Reverse)
+        }
+    }
+
+    #[inline]
+    pub fn from_bases_iter<I: Iterator<Item = T>>(&self, bases: I) -> T {
+        bases
+            .take(self.k)
+            .fold(T::zero(), |s, base| self.extend(s, base))
+    }
+
+    #[inline]
+    pub fn from_nucs(&self, nucs: &[u8]) -> T {
+        self.from_bases_iter(nucs.iter().filter_map(T::from_nuc))
+    }
+
+    pub fn to_bases(&self, kmer: T) -> Vec<T> {
+        (0..self.k)
+            .rev()
+            .map(|i| (kmer >> (2 * i)) & T::BASE_MASK)
+            .collect()
+    }
+
+    pub fn to_nucs(&self, kmer: T) -> Vec<u8> {
+        self.to_bases(kmer)
+            .into_iter()
+            .map(|base| base.to_nuc())
+            .collect()
+    }
+
+    pub fn iter_from_bases<I: Iterator<Item = T>>(&self, bases: I) -> DynKmerIterator<T, I> {
+        DynKmerIterator {
+            params: *self,
+            kmer: T::zero(),
+            bases,
+            init: false,
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn iter_from_nucs<'a, I: Iterator<Item = &'a u8>>(
+        &self,
+        nucs: I,
+    ) -> DynKmerIterator<T, FilterMap<I, fn(&u8) -> Option<T>>> {
+        self.iter_from_bases(nucs.filter_map(T::from_nuc))
+    }
+
+    pub fn lmer(&self, kmer: T) -> T {
+        fast_necklace_n(Auto::to_word(self.canonical(kmer), self.k), self.canon_bits)
+    }
+
+    pub fn lmer_index(&self, kmer: T) -> (T, usize) {
+        fast_necklace_index_n(Auto::to_word(self.canonical(kmer), self.k), self.canon_bits)
+    }
+
+    /// Necklace of the forward k-mer over 2K bits, without canonicalization
+    pub fn stranded_lmer(&self, kmer: T) -> T {
+        fast_necklace_n(kmer, self.kmer_bits)
+    }
+
+    pub fn stranded_lmer_index(&self, kmer: T) -> (T, usize) {
+        fast_necklace_index_n(kmer, self.kmer_bits)
+    }
+
+    /// Recover the forward k-mer from the output of `stranded_lmer_index`
+    pub fn from_stranded_lmer_index(&self, lmer: T, idx: usize) -> T {
+        let mut x = lmer;
+        for _ in 0..(idx % self.kmer_bits) {
+            x = bin_rot_left_n(x, self.kmer_bits);
+        }
+        x
+    }
+
+    /// Number of distinct rotations of the lmer
+    #[inline]
+    pub fn period(&self, lmer: T) -> usize {
+        period_n(lmer, self.canon_bits)
+    }
+
+    #[inline]
+    pub fn is_lyndon(&self, lmer: T) -> bool {
+        is_lyndon_n(lmer, self.canon_bits)
+    }
+
+    #[inline]
+    pub fn canonical_index(&self, lmer: T, idx: usize) -> usize {
+        canonical_index_n(lmer, idx, self.canon_bits)
+    }
+
+    /// All the canonical k-mers whose lmer is `lmer`, one per distinct rotation
+    pub fn expand_lmer(&self, lmer: T) -> impl Iterator<Item = T> + '_ {
+        (0..self.period(lmer))
+            .map(move |idx| self.from_lmer_index(lmer, idx))
+            .filter(|&kmer| self.is_canonical(kmer))
+    }
+
+    /// Recover the canonical k-mer from the output of `lmer_index`
+    pub fn from_lmer_index(&self, lmer: T, idx: usize) -> T {
+        let mut x = lmer;
+        for _ in 0..(idx % self.canon_bits) {
+            x = bin_rot_left_n(x, self.canon_bits);
+        }
+        Auto::from_word(x, self.k)
+    }
+}
+
+pub struct DynKmerIterator<T: Base, I: Iterator<Item = T>> {
+    params: DynKmer<T>,
+    kmer: T,
+    bases: I,
+    init: bool,
+}
+
+impl<T: Base, I: Iterator<Item = T>> Iterator for DynKmerIterator<T, I> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.init {
+            self.init = true;
+            for _ in 0..self.params.k {
+                self.kmer = self.params.extend(self.kmer, self.bases.next()?);
+            }
+            Some(self.kmer)
+        } else {
+            self.kmer = self.params.append(self.kmer, self.bases.next()?);
+            Some(self.kmer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::{Kmer, RawKmer, RevComp};
+    use crate::lyndon::{period, Lyndon};
+    use crate::utils::random_kmers;
+    use crate::wide::WideUint;
+
+    #[test]
+    fn test_constants() {
+        let params = DynKmer::<u64>::new(31);
+        assert_eq!(params.kmer_bits(), 62);
+        assert_eq!(params.canon_bits(), 61);
+        assert_eq!(params.rot_bits(), 6);
+        assert_eq!(params.lmer_bits(), 56);
+        assert_eq!(params.stranded_rot_bits(), 6);
+    }
+
+    #[test]
+    fn test_lmer_bits_fit() {
+        use crate::rank::num_necklaces;
+        for k in [5, 9, 15, 16, 21, 31, 32, 63] {
+            let params = DynKmer::<u128>::new(k);
+            assert!(num_necklaces(params.canon_bits()) <= 1 << params.lmer_bits());
+            assert!(num_necklaces(params.kmer_bits()) <= 1 << params.stranded_lmer_bits());
+        }
+    }
+
+    #[test]
+    fn test_dyn_kmer() {
+        const K: usize = 15;
+        let params = DynKmer::<u32>::new(K);
+        for kmer in random_kmers::<K, u32, RawKmer<K, u32>>(10_000) {
+            let x = kmer.to_int();
+            assert_eq!(params.rev_comp(x), kmer.rev_comp().to_int());
+            assert_eq!(params.lmer(x), kmer.lmer());
+            assert_eq!(params.lmer_index(x), kmer.lmer_index());
+            let (lmer, idx) = params.lmer_index(x);
+            assert_eq!(params.from_lmer_index(lmer, idx), params.canonical(x));
+            assert_eq!(params.period(lmer), period::<K, u32>(lmer));
+            assert_eq!(params.canonical_index(lmer, idx + params.period(lmer)), idx);
+            assert!(params.expand_lmer(lmer).any(|y| y == params.canonical(x)));
+            assert_eq!(params.stranded_lmer_index(x), kmer.stranded_lmer_index());
+            let (lmer, idx) = params.stranded_lmer_index(x);
+            assert_eq!(params.from_stranded_lmer_index(lmer, idx), x);
+        }
+    }
+
+    #[test]
+    fn test_dyn_kmer_wide() {
+        const K: usize = 81;
+        type T = WideUint<3>;
+        let params = DynKmer::<T>::new(K);
+        for kmer in random_kmers::<K, T, RawKmer<K, T>>(1000) {
+            let x = kmer.to_int();
+            assert_eq!(params.rev_comp(x), kmer.rev_comp().to_int());
+            assert_eq!(params.lmer(x), kmer.lmer());
+        }
+    }
+
+    #[test]
+    fn test_dyn_kmer_even() {
+        const K: usize = 16;
+        let params = DynKmer::<u64>::new(K);
+        assert_eq!(params.canon_bits(), 32);
+        for kmer in random_kmers::<K, u64, RawKmer<K, u64>>(10_000) {
+            let x = kmer.to_int();
+            assert_eq!(params.canonical(x), kmer.canonical().to_int());
+            let (canon, strand) = kmer.canonical_with_strand();
+            assert_eq!(params.canonical_with_strand(x), (canon.to_int(), strand));
+            assert_eq!(params.lmer_index(x), kmer.lmer_index());
+            let (lmer, idx) = params.lmer_index(x);
+            assert_eq!(params.from_lmer_index(lmer, idx), params.canonical(x));
+        }
+    }
+
+    #[test]
+    fn test_dyn_iter() {
+        let nucs = b"CATAATCCAGCTTGACCAGTACGATTACAGAGCATGCAT";
+        let params = DynKmer::<u64>::new(11);
+        let kmers: Vec<_> = params.iter_from_nucs(nucs.iter()).collect();
+        let raw_kmers: Vec<_> = RawKmer::<11, u64>::iter_from_nucs(nucs.iter())
+            .map(|kmer| kmer.to_int())
+            .collect();
+        assert_eq!(kmers, raw_kmers);
+        assert_eq!(params.to_nucs(kmers[0]), nucs[..11]);
+    }
+}
@@ -1,15 +1,46 @@
+use crate::canonical::{Auto, Canonicalizer};
+use crate::wide::WideUint;
 use core::fmt::{Binary, Display};
 use core::hash::Hash;
 use core::iter::FilterMap;
+use core::slice::Iter;
 use num_traits::int::PrimInt;
 
 pub trait Base: PrimInt + Display + Binary {
     const BASE_MASK: Self;
     fn from_nuc(b: &u8) -> Option<Self>;
+    /// Case-insensitive version of `from_nuc`
+    fn from_nuc_any_case(b: &u8) -> Option<Self>;
     fn to_nuc(self) -> u8;
     fn bases() -> [Self; 4];
 }
 
+/// How lowercase (soft-masked) nucleotides are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftMask {
+    /// Lowercase nucleotides are decoded like uppercase ones
+    Unmask,
+    /// Lowercase nucleotides are treated as invalid characters
+    Mask,
+}
+
+impl SoftMask {
+    #[inline]
+    pub fn decoder<T: Base>(self) -> fn(&u8) -> Option<T> {
+        match self {
+            Self::Unmask => T::from_nuc_any_case,
+            Self::Mask => T::from_nuc,
+        }
+    }
+}
+
+/// Orientation of a k-mer relative to its canonical form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
 pub trait RevComp {
     fn rev_comp(self) -> Self;
 }
@@ -42,13 +73,32 @@ pub trait Kmer<const K: usize, T: Base>: Sized + Copy + RevComp + Ord + Hash {
     fn predecessors(self) -> [Self; 4] {
         T::bases().map(|base| self.prepend(base))
     }
+    /// Parity of the k-mer for odd K, lexicographic order with its reverse complement for even K
     #[inline]
     fn is_canonical(self) -> bool {
-        self.to_int().count_ones() % 2 == 0
+        self.is_canonical_with::<Auto>()
     }
     #[inline]
     fn canonical(self) -> Self {
+        self.canonical_with::<Auto>()
+    }
+    #[inline]
+    fn is_canonical_with<C: Canonicalizer>(self) -> bool {
+        C::is_canonical(self)
+    }
+    /// Same as `canonical`, with the strand of the k-mer relative to its canonical form
+    #[inline]
+    fn canonical_with_strand(self) -> (Self, Strand) {
         if self.is_canonical() {
+            (self, Strand::Forward)
+        } else {
+            (self.rev_comp(), Strand::Reverse)
+        }
+    }
+    /// Canonical form of the k-mer according to the strategy `C`
+    #[inline]
+    fn canonical_with<C: Canonicalizer>(self) -> Self {
+        if self.is_canonical_with::<C>() {
             self
         } else {
             self.rev_comp()
@@ -93,6 +143,60 @@ pub trait Kmer<const K: usize, T: Base>: Sized + Copy + RevComp + Ord + Hash {
     ) -> KmerIterator<K, T, Self, FilterMap<I, fn(&u8) -> Option<T>>> {
         Self::iter_from_bases(nucs.filter_map(T::from_nuc))
     }
+    /// One `KmerIterator` per run of `nucs` decoded according to `mask`,
+    /// so that no k-mer spans a rejected (e.g. soft-masked) character
+    #[allow(clippy::type_complexity)]
+    fn iter_runs_from_nucs_with(
+        nucs: &[u8],
+        mask: SoftMask,
+    ) -> impl Iterator<Item = KmerIterator<K, T, Self, FilterMap<Iter<'_, u8>, fn(&u8) -> Option<T>>>>
+    {
+        let decode = mask.decoder();
+        nucs.split(move |nuc| decode(nuc).is_none())
+            .filter(|run| run.len() >= K)
+            .map(move |run| Self::iter_from_bases(run.iter().filter_map(decode)))
+    }
+    /// Iterate over the canonical k-mers of `bases` with their strand,
+    /// updating the reverse complement incrementally
+    fn iter_canonical_from_bases<I: Iterator<Item = T>>(
+        bases: I,
+    ) -> CanonicalKmerIterator<K, T, Self, I> {
+        CanonicalKmerIterator {
+            kmer: Self::new(),
+            rc: Self::new(),
+            bases,
+            init: false,
+        }
+    }
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    fn iter_canonical_from_nucs<'a, I: Iterator<Item = &'a u8>>(
+        nucs: I,
+    ) -> CanonicalKmerIterator<K, T, Self, FilterMap<I, fn(&u8) -> Option<T>>> {
+        Self::iter_canonical_from_bases(nucs.filter_map(T::from_nuc))
+    }
+    /// Iterate over the k-mers of `nucs` without spanning invalid nucleotides:
+    /// the window is reset at each character rejected by `Base::from_nuc`
+    #[inline]
+    fn iter_split_from_nucs<'a, I: Iterator<Item = &'a u8>>(
+        nucs: I,
+    ) -> SplitKmerIterator<'a, K, T, Self, I> {
+        Self::iter_split_from_nucs_with(nucs, SoftMask::Mask)
+    }
+    /// Same as `iter_split_from_nucs`, decoding lowercase nucleotides according to `mask`
+    #[inline]
+    fn iter_split_from_nucs_with<'a, I: Iterator<Item = &'a u8>>(
+        nucs: I,
+        mask: SoftMask,
+    ) -> SplitKmerIterator<'a, K, T, Self, I> {
+        SplitKmerIterator {
+            kmer: Self::new(),
+            nucs,
+            decode: mask.decoder(),
+            len: 0,
+            pos: 0,
+        }
+    }
 }
 
 pub struct KmerIterator<const K: usize, T, KT, I>
@@ -127,6 +231,172 @@ where
     }
 }
 
+pub struct CanonicalKmerIterator<const K: usize, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>,
+{
+    kmer: KT,
+    rc: KT,
+    bases: I,
+    init: bool,
+}
+
+impl<const K: usize, T, KT, I> CanonicalKmerIterator<K, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>,
+{
+    #[inline]
+    fn push(&mut self, base: T) {
+        self.kmer = self.kmer.append(base);
+        // complementary bases differ by their upper bit
+        self.rc = self.rc.prepend(base ^ (T::one() << 1));
+    }
+
+    #[inline]
+    fn canonical(&self) -> (KT, Strand) {
+        // same as `is_canonical`, reusing the rolling reverse complement for even K
+        let forward = if K % 2 == 1 {
+            self.kmer.is_canonical()
+        } else {
+            self.kmer <= self.rc
+        };
+        if forward {
+            (self.kmer, Strand::Forward)
+        } else {
+            (self.rc, Strand::Reverse)
+        }
+    }
+}
+
+impl<const K: usize, T, KT, I> Iterator for CanonicalKmerIterator<K, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>,
+{
+    type Item = (KT, Strand);
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.init {
+            self.init = true;
+            for _ in 0..K {
+                let base = self.bases.next()?;
+                self.push(base);
+            }
+        } else {
+            let base = self.bases.next()?;
+            self.push(base);
+        }
+        Some(self.canonical())
+    }
+}
+
+pub struct SplitKmerIterator<'a, const K: usize, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = &'a u8>,
+{
+    kmer: KT,
+    nucs: I,
+    decode: fn(&u8) -> Option<T>,
+    len: usize,
+    pos: usize,
+}
+
+impl<'a, const K: usize, T, KT, I> SplitKmerIterator<'a, K, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = &'a u8>,
+{
+    /// Also yield the position of each k-mer in the sequence
+    pub fn with_positions(self) -> PosKmerIterator<'a, K, T, KT, I> {
+        PosKmerIterator(self)
+    }
+
+    /// Yield the canonical k-mers with their position and strand in the sequence
+    pub fn canonical_with_positions(self) -> PosCanonicalKmerIterator<'a, K, T, KT, I> {
+        PosCanonicalKmerIterator(self)
+    }
+
+    fn next_with_pos(&mut self) -> Option<(usize, KT)> {
+        loop {
+            let nuc = self.nucs.next()?;
+            self.pos += 1;
+            if let Some(base) = (self.decode)(nuc) {
+                self.kmer = self.kmer.append(base);
+                if self.len < K {
+                    self.len += 1;
+                }
+                if self.len == K {
+                    return Some((self.pos - K, self.kmer));
+                }
+            } else {
+                self.len = 0;
+            }
+        }
+    }
+}
+
+impl<'a, const K: usize, T, KT, I> Iterator for SplitKmerIterator<'a, K, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = &'a u8>,
+{
+    type Item = KT;
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_pos().map(|(_, kmer)| kmer)
+    }
+}
+
+pub struct PosKmerIterator<'a, const K: usize, T, KT, I>(SplitKmerIterator<'a, K, T, KT, I>)
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = &'a u8>;
+
+impl<'a, const K: usize, T, KT, I> Iterator for PosKmerIterator<'a, K, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = &'a u8>,
+{
+    type Item = (usize, KT);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_pos()
+    }
+}
+
+pub struct PosCanonicalKmerIterator<'a, const K: usize, T, KT, I>(
+    SplitKmerIterator<'a, K, T, KT, I>,
+)
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = &'a u8>;
+
+impl<'a, const K: usize, T, KT, I> Iterator for PosCanonicalKmerIterator<'a, K, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = &'a u8>,
+{
+    type Item = (usize, KT, Strand);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (pos, kmer) = self.0.next_with_pos()?;
+        let (canon, strand) = kmer.canonical_with_strand();
+        Some((pos, canon, strand))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RawKmer<const K: usize, T: Base>(T);
 
@@ -142,6 +412,13 @@ macro_rules! impl_t {
             }
         }
         #[inline]
+        fn from_nuc_any_case(b: &u8) -> Option<Self> {
+            match b {
+                b'A' | b'C' | b'G' | b'T' | b'a' | b'c' | b'g' | b't' => Some(((b / 2) % 4) as $T),
+                _ => None,
+            }
+        }
+        #[inline]
         fn to_nuc(self) -> u8 {
             const BASE_LOOKUP: [u8; 4] = [b'A', b'C', b'T', b'G'];
             debug_assert!(self < 4, "Invalid base");
@@ -154,7 +431,10 @@ macro_rules! impl_t {
     }
 
     impl<const K: usize> Kmer<K, $T> for RawKmer<K, $T> {
-        const MASK: $T = (1 << (2 * K)) - 1;
+        const MASK: $T = match (1 as $T).checked_shl(2 * K as u32) {
+            Some(x) => x - 1,
+            None => <$T>::MAX,
+        };
         #[inline(always)]
         fn from_int(s: $T) -> Self {
             Self(s)
@@ -276,9 +556,54 @@ impl<const K: usize> RevComp for RawKmer<K, u128> {
     }
 }
 
+impl<const W: usize> Base for WideUint<W> {
+    const BASE_MASK: Self = Self::from_u64(0b11);
+    #[inline]
+    fn from_nuc(b: &u8) -> Option<Self> {
+        u64::from_nuc(b).map(Self::from_u64)
+    }
+    #[inline]
+    fn from_nuc_any_case(b: &u8) -> Option<Self> {
+        u64::from_nuc_any_case(b).map(Self::from_u64)
+    }
+    #[inline]
+    fn to_nuc(self) -> u8 {
+        debug_assert!(self < Self::from_u64(4), "Invalid base");
+        self.0[W - 1].to_nuc()
+    }
+    #[inline(always)]
+    fn bases() -> [Self; 4] {
+        u64::bases().map(Self::from_u64)
+    }
+}
+
+impl<const K: usize, const W: usize> Kmer<K, WideUint<W>> for RawKmer<K, WideUint<W>> {
+    const MASK: WideUint<W> = WideUint::low_mask(2 * K);
+    #[inline(always)]
+    fn from_int(s: WideUint<W>) -> Self {
+        Self(s)
+    }
+    #[inline(always)]
+    fn to_int(self) -> WideUint<W> {
+        self.0
+    }
+}
+
+impl<const K: usize, const W: usize> RevComp for RawKmer<K, WideUint<W>> {
+    fn rev_comp(self) -> Self {
+        let mut words = self
+            .to_int()
+            .0
+            .map(|w| RawKmer::<32, u64>::from_int(w).rev_comp().to_int());
+        words.reverse();
+        Self::from_int(WideUint(words) >> (2 * (32 * W - K)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::random_kmers;
 
     #[test]
     fn test_rc_8() {
@@ -306,6 +631,131 @@ mod tests {
         assert_eq!(kmer.rev_comp().to_nucs(), *b"GCTGGATTATG");
     }
     #[test]
+    fn test_rc_wide() {
+        let kmer = RawKmer::<11, WideUint<1>>::from_nucs(b"CATAATCCAGC");
+        assert_eq!(kmer.rev_comp().to_nucs(), *b"GCTGGATTATG");
+        let nucs = b"CATAATCCAGCTTGACCAGTACGATTACAGAGCATGCATCCAGGATTACAGAGGACGTACGTACCAG";
+        let mut rc_nucs = nucs.map(|nuc| match nuc {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            _ => b'A',
+        });
+        rc_nucs.reverse();
+        let kmer = RawKmer::<67, WideUint<3>>::from_nucs(nucs);
+        assert_eq!(kmer.to_nucs(), *nucs);
+        assert_eq!(kmer.rev_comp().to_nucs(), rc_nucs);
+    }
+    #[test]
+    fn test_split_iter() {
+        let nucs = b"ACGTNACGTACNNGTAC";
+        let kmers: Vec<_> = RawKmer::<4, u8>::iter_split_from_nucs(nucs.iter())
+            .with_positions()
+            .map(|(pos, kmer)| (pos, kmer.to_nucs()))
+            .collect();
+        assert_eq!(
+            kmers,
+            vec![
+                (0, *b"ACGT"),
+                (5, *b"ACGT"),
+                (6, *b"CGTA"),
+                (7, *b"GTAC"),
+                (13, *b"GTAC"),
+            ]
+        );
+        let kmers: Vec<_> = RawKmer::<4, u8>::iter_split_from_nucs(nucs.iter()).collect();
+        assert_eq!(kmers.len(), 5);
+        assert_eq!(
+            RawKmer::<4, u8>::iter_split_from_nucs(b"ACGNNA".iter()).next(),
+            None
+        );
+        let kmers: Vec<_> = RawKmer::<3, u8>::iter_split_from_nucs(b"ACNTTTG".iter())
+            .canonical_with_positions()
+            .map(|(pos, kmer, strand)| (pos, kmer.to_nucs(), strand))
+            .collect();
+        assert_eq!(
+            kmers,
+            vec![(3, *b"AAA", Strand::Reverse), (4, *b"TTG", Strand::Forward)]
+        );
+    }
+    #[test]
+    fn test_soft_mask() {
+        let nucs = b"ACGTacgtACG";
+        let kmer =
+            RawKmer::<11, u32>::from_bases_iter(nucs.iter().filter_map(SoftMask::Unmask.decoder()));
+        assert_eq!(kmer.to_nucs(), *b"ACGTACGTACG");
+        let kmers: Vec<_> =
+            RawKmer::<3, u8>::iter_split_from_nucs_with(nucs.iter(), SoftMask::Mask)
+                .with_positions()
+                .map(|(pos, kmer)| (pos, kmer.to_nucs()))
+                .collect();
+        assert_eq!(kmers, vec![(0, *b"ACG"), (1, *b"CGT"), (8, *b"ACG")]);
+        let n_kmers =
+            RawKmer::<3, u8>::iter_split_from_nucs_with(nucs.iter(), SoftMask::Unmask).count();
+        assert_eq!(n_kmers, 9);
+        // k-mers of different runs are not joined across the masked bases
+        let runs: Vec<Vec<_>> = RawKmer::<3, u8>::iter_runs_from_nucs_with(nucs, SoftMask::Mask)
+            .map(|run| run.map(|kmer| kmer.to_nucs()).collect())
+            .collect();
+        assert_eq!(runs, vec![vec![*b"ACG", *b"CGT"], vec![*b"ACG"]]);
+        let n_kmers = RawKmer::<3, u8>::iter_runs_from_nucs_with(nucs, SoftMask::Unmask)
+            .flatten()
+            .count();
+        assert_eq!(n_kmers, 9);
+        let runs = RawKmer::<3, u8>::iter_runs_from_nucs_with(b"ACNTTTGa", SoftMask::Mask);
+        assert_eq!(runs.flatten().count(), 2);
+    }
+    #[test]
+    fn test_canonical_iter() {
+        let kmers = random_kmers::<15, u32, RawKmer<15, u32>>(10_000);
+        let bases = kmers[0]
+            .to_bases()
+            .into_iter()
+            .chain(kmers[1..].iter().map(|kmer| kmer.to_int() & 0b11));
+        for (kmer, (canon, strand)) in kmers
+            .iter()
+            .zip(RawKmer::<15, u32>::iter_canonical_from_bases(bases))
+        {
+            assert_eq!(canon, kmer.canonical());
+            assert_eq!((canon, strand), kmer.canonical_with_strand());
+            match strand {
+                Strand::Forward => assert_eq!(canon, *kmer),
+                Strand::Reverse => assert_eq!(canon, kmer.rev_comp()),
+            }
+        }
+    }
+    #[test]
+    fn test_canonical_iter_even() {
+        let kmers = random_kmers::<16, u32, RawKmer<16, u32>>(10_000);
+        let bases = kmers[0]
+            .to_bases()
+            .into_iter()
+            .chain(kmers[1..].iter().map(|kmer| kmer.to_int() & 0b11));
+        for (kmer, res) in kmers
+            .iter()
+            .zip(RawKmer::<16, u32>::iter_canonical_from_bases(bases))
+        {
+            assert_eq!(res, kmer.canonical_with_strand());
+        }
+        // palindromes are read on the forward strand
+        let palindrome = RawKmer::<4, u8>::from_nucs(b"ACGT");
+        assert_eq!(
+            RawKmer::<4, u8>::iter_canonical_from_nucs(b"ACGT".iter()).next(),
+            Some((palindrome, Strand::Forward))
+        );
+    }
+    #[test]
+    fn test_canonical_iter_nucs() {
+        let nucs = b"CATAATCCAGCTTGACCAGTACGATTACAGAGCATGCATCCAGGATTACAGAGGACG";
+        let kmers: Vec<_> = RawKmer::<31, WideUint<1>>::iter_from_nucs(nucs.iter())
+            .map(|kmer| kmer.canonical())
+            .collect();
+        let canon: Vec<_> = RawKmer::<31, WideUint<1>>::iter_canonical_from_nucs(nucs.iter())
+            .map(|(kmer, _)| kmer)
+            .collect();
+        assert_eq!(canon, kmers);
+    }
+    #[test]
     fn rc_rc_8() {
         for i in 0..64 {
             let kmer = RawKmer::<3, u8>::from_int(i);
@@ -340,4 +790,10 @@ mod tests {
             assert_eq!(kmer.rev_comp().rev_comp().to_int(), i);
         }
     }
+    #[test]
+    fn rc_rc_wide() {
+        for kmer in random_kmers::<101, WideUint<4>, RawKmer<101, WideUint<4>>>(10_000) {
+            assert_eq!(kmer.rev_comp().rev_comp(), kmer);
+        }
+    }
 }
@@ -1,10 +1,17 @@
+pub mod bracelet;
+pub mod canonical;
+pub mod debruijn;
+pub mod dispatch;
+pub mod dynamic;
 pub mod kmer;
 pub mod lyndon;
 pub mod necklace;
 pub mod partition;
+pub mod quaternary;
 pub mod rank;
 pub mod reads;
 pub mod utils;
+pub mod wide;
 
 // Loads runtime-provided constants for which declarations
 // will be generated at `$OUT_DIR/constants.rs`.
@@ -1,27 +1,59 @@
-use crate::kmer::{Base, Kmer};
+use crate::canonical::{canon_bits, Auto, Canonicalizer};
+use crate::kmer::{Base, Kmer, Strand};
+use crate::necklace::{FrontNecklaceQueue, NecklaceQueue};
+use crate::rank::{Ranker, Workspace};
+use crate::utils::low_mask;
 use core::cmp::min;
+use core::iter::FilterMap;
 
 #[inline]
 pub fn bin_rot_right<const K: usize, T: Base>(x: T) -> T {
-    ((x & T::one()) << (2 * K - 2)) | (x >> 1)
+    bin_rot_right_n(x, canon_bits(K))
+}
+
+#[inline]
+pub fn bin_rot_left<const K: usize, T: Base>(x: T) -> T {
+    bin_rot_left_n(x, canon_bits(K))
 }
 
 pub fn necklace<const K: usize, T: Base>(x: T) -> T {
+    necklace_n(x, canon_bits(K))
+}
+
+pub fn necklace_index<const K: usize, T: Base>(x: T) -> (T, usize) {
+    necklace_index_n(x, canon_bits(K))
+}
+
+/// Rotate the `n`-bit word `x` one bit to the right
+#[inline]
+pub fn bin_rot_right_n<T: Base>(x: T, n: usize) -> T {
+    ((x & T::one()) << (n - 1)) | (x >> 1)
+}
+
+/// Rotate the `n`-bit word `x` one bit to the left
+#[inline]
+pub fn bin_rot_left_n<T: Base>(x: T, n: usize) -> T {
+    ((x << 1) & low_mask(n)) | (x >> (n - 1))
+}
+
+/// Smallest rotation of the `n`-bit word `x`
+pub fn necklace_n<T: Base>(x: T, n: usize) -> T {
     let mut res = x;
     let mut rot = x;
-    for _ in 1..(2 * K - 1) {
-        rot = bin_rot_right::<K, T>(rot);
+    for _ in 1..n {
+        rot = bin_rot_right_n(rot, n);
         res = min(res, rot);
     }
     res
 }
 
-pub fn necklace_index<const K: usize, T: Base>(x: T) -> (T, usize) {
+/// Smallest rotation of the `n`-bit word `x` with the number of right rotations to reach it
+pub fn necklace_index_n<T: Base>(x: T, n: usize) -> (T, usize) {
     let mut res = x;
     let mut rot = x;
     let mut idx = 0;
-    for i in 1..(2 * K - 1) {
-        rot = bin_rot_right::<K, T>(rot);
+    for i in 1..n {
+        rot = bin_rot_right_n(rot, n);
         if rot < res {
             res = rot;
             idx = i;
@@ -30,21 +62,430 @@ pub fn necklace_index<const K: usize, T: Base>(x: T) -> (T, usize) {
     (res, idx)
 }
 
+#[inline]
+pub fn fast_necklace<const K: usize, T: Base>(x: T) -> T {
+    fast_necklace_n(x, canon_bits(K))
+}
+
+#[inline]
+pub fn fast_necklace_index<const K: usize, T: Base>(x: T) -> (T, usize) {
+    fast_necklace_index_n(x, canon_bits(K))
+}
+
+/// Rotate the `n`-bit word `x` `p` bits to the left
+#[inline]
+fn rot_left_n<T: Base>(x: T, n: usize, p: usize) -> T {
+    if p == 0 {
+        x
+    } else {
+        ((x << p) & low_mask(n)) | (x >> (n - p))
+    }
+}
+
+/// Smallest left rotation of the `n`-bit word `x` reaching its necklace.
+/// Two-pointer least rotation where candidate rotations are compared as
+/// integers, and their longest common prefix is read from the leading zeros
+/// of their xor, so that every step skips a whole mismatching block.
+pub fn least_rotation_n<T: Base>(x: T, n: usize) -> usize {
+    let pad = T::zero().count_zeros() as usize - n;
+    let (mut i, mut j) = (0, 1);
+    while i < n && j < n {
+        let (u, v) = (rot_left_n(x, n, i), rot_left_n(x, n, j));
+        if u == v {
+            break;
+        }
+        let lcp = (u ^ v).leading_zeros() as usize - pad;
+        if u > v {
+            i += lcp + 1;
+        } else {
+            j += lcp + 1;
+        }
+        if i == j {
+            j += 1;
+        }
+    }
+    min(i, j)
+}
+
+/// Same as `necklace_n` in linear time
+#[inline]
+pub fn fast_necklace_n<T: Base>(x: T, n: usize) -> T {
+    rot_left_n(x, n, least_rotation_n(x, n))
+}
+
+/// Same as `necklace_index_n` in linear time
+pub fn fast_necklace_index_n<T: Base>(x: T, n: usize) -> (T, usize) {
+    let p = least_rotation_n(x, n);
+    let res = rot_left_n(x, n, p);
+    // the smallest right rotation comes from the last occurrence of the necklace
+    (res, (n - p) % period_n(res, n))
+}
+
+#[inline]
+pub fn period<const K: usize, T: Base>(x: T) -> usize {
+    period_n(x, canon_bits(K))
+}
+
+#[inline]
+pub fn is_lyndon<const K: usize, T: Base>(x: T) -> bool {
+    is_lyndon_n(x, canon_bits(K))
+}
+
+#[inline]
+pub fn canonical_index<const K: usize, T: Base>(x: T, idx: usize) -> usize {
+    canonical_index_n(x, idx, canon_bits(K))
+}
+
+/// Smallest period of the `n`-bit word `x`, i.e. its number of distinct rotations
+pub fn period_n<T: Base>(x: T, n: usize) -> usize {
+    (1..n)
+        .filter(|&d| n.is_multiple_of(d))
+        .find(|&d| rot_left_n(x, n, d) == x)
+        .unwrap_or(n)
+}
+
+/// Check that the `n`-bit word `x` is a Lyndon word, i.e. an aperiodic necklace
+#[inline]
+pub fn is_lyndon_n<T: Base>(x: T, n: usize) -> bool {
+    least_rotation_n(x, n) == 0 && period_n(x, n) == n
+}
+
+/// Smallest rotation index equivalent to `idx` for the `n`-bit necklace `x`,
+/// so that `(x, idx)` identifies a single rotation
+#[inline]
+pub fn canonical_index_n<T: Base>(x: T, idx: usize, n: usize) -> usize {
+    idx % period_n(x, n)
+}
+
+/// Next `n`-bit prenecklace after the prenecklace `w` in lex order with its period,
+/// following the FKM algorithm: increment the last 0 and extend periodically
+pub(crate) fn next_prenecklace_n<T: Base>(w: T, n: usize) -> Option<(T, usize)> {
+    let t = (!w).trailing_zeros() as usize;
+    if t >= n {
+        return None;
+    }
+    let p = n - t;
+    let mut res = ((w >> t) | T::one()) << t;
+    let mut len = p;
+    while len < n {
+        res = res | (res >> len);
+        len *= 2;
+    }
+    Some((res, p))
+}
+
+/// Next `n`-bit necklace after the necklace `w` in lex order
+pub fn next_necklace_n<T: Base>(w: T, n: usize) -> Option<T> {
+    let mut w = w;
+    loop {
+        let (next, p) = next_prenecklace_n(w, n)?;
+        // a prenecklace is a necklace iff its period divides n
+        if n.is_multiple_of(p) {
+            return Some(next);
+        }
+        w = next;
+    }
+}
+
+/// Next `n`-bit Lyndon word after the word `w` in lex order
+pub fn next_lyndon_word_n<T: Base>(w: T, n: usize) -> Option<T> {
+    let mut w = w;
+    loop {
+        let (next, p) = next_prenecklace_n(w, n)?;
+        if p == n {
+            return Some(next);
+        }
+        w = next;
+    }
+}
+
+/// Lazy iterator over the `n`-bit necklaces in lex order
+pub fn necklaces_n<T: Base>(n: usize) -> Necklaces<T> {
+    Necklaces {
+        n,
+        next: Some(T::zero()),
+        lyndon: false,
+    }
+}
+
+/// Lazy iterator over the `n`-bit Lyndon words in lex order
+pub fn lyndon_words_n<T: Base>(n: usize) -> Necklaces<T> {
+    Necklaces {
+        n,
+        // 0 is the only Lyndon word made of 0s
+        next: if n == 1 {
+            Some(T::zero())
+        } else {
+            next_lyndon_word_n(T::zero(), n)
+        },
+        lyndon: true,
+    }
+}
+
+pub struct Necklaces<T: Base> {
+    n: usize,
+    next: Option<T>,
+    lyndon: bool,
+}
+
+impl<T: Base> Iterator for Necklaces<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.next?;
+        self.next = if self.lyndon {
+            next_lyndon_word_n(res, self.n)
+        } else {
+            next_necklace_n(res, self.n)
+        };
+        Some(res)
+    }
+}
+
 pub trait Lyndon<const K: usize, T: Base>: Kmer<K, T> {
+    /// Necklace of the canonical k-mer over `canon_bits(K)` bits, dropping the
+    /// parity bit for odd K and keeping the whole k-mer for even K
     fn lmer(self) -> T {
-        necklace::<K, T>(self.canonical().to_int() >> 1)
+        self.lmer_with::<Auto>()
     }
+    /// Lmer with the smallest number of right rotations reaching it,
+    /// which is always less than the period of the lmer
     fn lmer_index(self) -> (T, usize) {
-        necklace_index::<K, T>(self.canonical().to_int() >> 1)
+        self.lmer_index_with::<Auto>()
+    }
+    /// Recover the canonical k-mer from the output of `lmer_index`
+    fn from_lmer_index(lmer: T, idx: usize) -> Self {
+        Self::from_lmer_index_with::<Auto>(lmer, idx)
+    }
+    /// Same as `lmer_index` with the strand of the k-mer relative to its canonical form
+    fn lmer_index_with_strand(self) -> (T, usize, Strand) {
+        let strand = self.canonical_with_strand().1;
+        let (lmer, idx) = self.lmer_index();
+        (lmer, idx, strand)
+    }
+    /// Recover the k-mer itself from the output of `lmer_index_with_strand`
+    fn from_lmer_index_with_strand(lmer: T, idx: usize, strand: Strand) -> Self {
+        let kmer = Self::from_lmer_index(lmer, idx);
+        match strand {
+            Strand::Forward => kmer,
+            Strand::Reverse => kmer.rev_comp(),
+        }
+    }
+    /// Lmer of the k-mer made canonical by the strategy `C`, over `C::word_bits(K)` bits
+    fn lmer_with<C: Canonicalizer>(self) -> T {
+        let word = C::to_word(self.canonical_with::<C>().to_int(), K);
+        fast_necklace_n(word, C::word_bits(K))
+    }
+    /// Same as `lmer_index` for `lmer_with`
+    fn lmer_index_with<C: Canonicalizer>(self) -> (T, usize) {
+        let word = C::to_word(self.canonical_with::<C>().to_int(), K);
+        fast_necklace_index_n(word, C::word_bits(K))
+    }
+    /// Recover the canonical k-mer from the output of `lmer_index_with`
+    fn from_lmer_index_with<C: Canonicalizer>(lmer: T, idx: usize) -> Self {
+        let n = C::word_bits(K);
+        Self::from_int(C::from_word(rot_left_n(lmer, n, idx % n), K))
+    }
+    /// Necklace of the forward k-mer over 2K bits, without canonicalization
+    fn stranded_lmer(self) -> T {
+        fast_necklace_n(self.to_int(), 2 * K)
+    }
+    /// Same as `lmer_index` for `stranded_lmer`
+    fn stranded_lmer_index(self) -> (T, usize) {
+        fast_necklace_index_n(self.to_int(), 2 * K)
+    }
+    /// Recover the forward k-mer from the output of `stranded_lmer_index`
+    fn from_stranded_lmer_index(lmer: T, idx: usize) -> Self {
+        Self::from_int(rot_left_n(lmer, 2 * K, idx % (2 * K)))
+    }
+    /// All the canonical k-mers whose lmer is `lmer`, one per distinct rotation.
+    /// Every rotation is canonical for odd K, while for even K some are skipped.
+    fn expand_lmer(lmer: T) -> impl Iterator<Item = Self> {
+        (0..period::<K, T>(lmer))
+            .map(move |idx| Self::from_lmer_index(lmer, idx))
+            .filter(|kmer| kmer.is_canonical())
+    }
+    /// Dense code in `[0, 2^(2K-1))` of the canonical k-mer, ordered by lmer.
+    /// Odd K is needed for the codes to be dense: for even K they lie in
+    /// `[0, 2^2K)` and only the codes of canonical k-mers are used.
+    #[inline]
+    fn dense_code<const N: usize>(self, ranker: &Ranker<N, T>) -> T {
+        ranker.with_workspace(|ws| self.dense_code_with(ranker, ws))
+    }
+    /// Recover the canonical k-mer from the output of `dense_code`
+    #[inline]
+    fn from_dense_code<const N: usize>(code: T, ranker: &Ranker<N, T>) -> Self {
+        ranker.with_workspace(|ws| Self::from_dense_code_with(code, ranker, ws))
+    }
+    /// Same as `dense_code`, using the given workspace
+    #[inline]
+    fn dense_code_with<const N: usize>(self, ranker: &Ranker<N, T>, ws: &mut Workspace<T>) -> T {
+        let (lmer, idx) = self.lmer_index();
+        ranker.encode_with(lmer, idx, ws)
+    }
+    /// Same as `from_dense_code`, using the given workspace
+    #[inline]
+    fn from_dense_code_with<const N: usize>(
+        code: T,
+        ranker: &Ranker<N, T>,
+        ws: &mut Workspace<T>,
+    ) -> Self {
+        let (lmer, idx) = ranker.decode_with(code, ws);
+        Self::from_lmer_index(lmer, idx)
+    }
+    /// Stream the output of `lmer_index` for each k-mer of `bases`, using
+    /// necklace queues over windows of `W` rotations (`N` must be `canon_bits(K)`)
+    fn iter_lmer_index_from_bases<const N: usize, const W: usize, I: Iterator<Item = T>>(
+        bases: I,
+    ) -> LmerIterator<K, N, W, T, Self, I> {
+        LmerIterator::new(bases)
+    }
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    fn iter_lmer_index_from_nucs<'a, const N: usize, const W: usize, I: Iterator<Item = &'a u8>>(
+        nucs: I,
+    ) -> LmerIterator<K, N, W, T, Self, FilterMap<I, fn(&u8) -> Option<T>>> {
+        Self::iter_lmer_index_from_bases(nucs.filter_map(T::from_nuc))
     }
 }
 
 impl<const K: usize, T: Base, KT: Kmer<K, T>> Lyndon<K, T> for KT {}
 
+/// Iterator over the `(lmer, index)` pairs of the canonical k-mers of a sequence.
+/// Rotations are maintained by sliding necklace queues over both strands, where
+/// the reverse strand inserts the complemented bases at the front of its word.
+pub struct LmerIterator<const K: usize, const N: usize, const W: usize, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>,
+{
+    bases: I,
+    queue: NecklaceQueue<N, W, T>,
+    rc_queue: FrontNecklaceQueue<N, W, T>,
+    kmer: KT,
+    rc: KT,
+    len: usize,
+}
+
+impl<const K: usize, const N: usize, const W: usize, T, KT, I> LmerIterator<K, N, W, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>,
+{
+    fn new(bases: I) -> Self {
+        assert_eq!(N, canon_bits(K), "N must be canon_bits(K)");
+        assert!(W >= 1 && W <= N, "W must be in 1..=N");
+        Self {
+            bases,
+            queue: NecklaceQueue::new(),
+            rc_queue: FrontNecklaceQueue::new(),
+            kmer: KT::new(),
+            rc: KT::new(),
+            len: 0,
+        }
+    }
+
+    /// Bits entering the word when `base` is appended, given the last bit of the previous k-mer
+    #[inline]
+    fn shifted_bits(bit: T, base: T) -> T {
+        if K % 2 == 1 {
+            (bit << 1) | (base >> 1)
+        } else {
+            base
+        }
+    }
+
+    #[inline]
+    fn lmer_index((lmer, p): (T, usize)) -> (T, usize) {
+        // smallest right rotation reaching the necklace
+        (lmer, (N - p) % N % period_n(lmer, N))
+    }
+
+    /// Also yield the strand of each k-mer, as in `lmer_index_with_strand`
+    pub fn with_strand(self) -> LmerStrandIterator<K, N, W, T, KT, I> {
+        LmerStrandIterator(self)
+    }
+
+    fn next_with_strand(&mut self) -> Option<(T, usize, Strand)> {
+        loop {
+            let base = self.bases.next()?;
+            let bit = self.kmer.to_int() & T::one();
+            // complementary bases differ by their upper bit
+            let rc_base = base ^ (T::one() << 1);
+            self.kmer = self.kmer.append(base);
+            self.rc = self.rc.prepend(rc_base);
+            if self.len == K {
+                self.queue.insert2(Self::shifted_bits(bit, base));
+                // the top bits of the reverse word are the complemented base
+                self.rc_queue.insert2(rc_base);
+            } else {
+                self.len += 1;
+                if self.len < K {
+                    continue;
+                }
+                self.queue.insert_full(Auto::to_word(self.kmer.to_int(), K));
+                self.rc_queue
+                    .insert_full(Auto::to_word(self.rc.to_int(), K));
+            }
+            // same as `is_canonical`, reusing the rolling reverse complement for even K
+            let forward = if K % 2 == 1 {
+                self.kmer.is_canonical()
+            } else {
+                self.kmer <= self.rc
+            };
+            return Some(if forward {
+                let (lmer, idx) = Self::lmer_index(self.queue.get_necklace_pos());
+                (lmer, idx, Strand::Forward)
+            } else {
+                let (lmer, idx) = Self::lmer_index(self.rc_queue.get_necklace_pos());
+                (lmer, idx, Strand::Reverse)
+            });
+        }
+    }
+}
+
+impl<const K: usize, const N: usize, const W: usize, T, KT, I> Iterator
+    for LmerIterator<K, N, W, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>,
+{
+    type Item = (T, usize);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_strand().map(|(lmer, idx, _)| (lmer, idx))
+    }
+}
+
+pub struct LmerStrandIterator<const K: usize, const N: usize, const W: usize, T, KT, I>(
+    LmerIterator<K, N, W, T, KT, I>,
+)
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>;
+
+impl<const K: usize, const N: usize, const W: usize, T, KT, I> Iterator
+    for LmerStrandIterator<K, N, W, T, KT, I>
+where
+    T: Base,
+    KT: Kmer<K, T>,
+    I: Iterator<Item = T>,
+{
+    type Item = (T, usize, Strand);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_strand()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::kmer::{RawKmer, RevComp};
+    use std::collections::BTreeSet;
 
     #[test]
     fn test_lmer_rc() {
@@ -53,4 +494,316 @@ mod tests {
             assert_eq!(kmer.lmer(), kmer.rev_comp().lmer());
         }
     }
+
+    #[test]
+    fn test_lmer_rc_wide() {
+        use crate::utils::random_kmers;
+        use crate::wide::WideUint;
+        type T = WideUint<3>;
+        for kmer in random_kmers::<81, T, RawKmer<81, T>>(1000) {
+            assert_eq!(kmer.lmer(), kmer.rev_comp().lmer());
+            let (lmer, idx) = kmer.lmer_index();
+            assert_eq!(
+                RawKmer::<81, T>::from_lmer_index(lmer, idx),
+                kmer.canonical()
+            );
+        }
+    }
+
+    #[test]
+    fn test_lmer_iter() {
+        use crate::utils::random_kmers;
+        const K: usize = 15;
+        const N: usize = 2 * K - 1;
+        type KT = RawKmer<K, u32>;
+        let kmers = random_kmers::<K, u32, KT>(10_000);
+        let bases: Vec<_> = kmers[0]
+            .to_bases()
+            .into_iter()
+            .chain(kmers[1..].iter().map(|kmer| kmer.to_int() & 0b11))
+            .collect();
+        let lmers: Vec<_> = kmers.iter().map(|kmer| kmer.lmer_index()).collect();
+        let bases = || bases.iter().copied();
+        assert_eq!(
+            KT::iter_lmer_index_from_bases::<N, 1, _>(bases()).collect::<Vec<_>>(),
+            lmers
+        );
+        assert_eq!(
+            KT::iter_lmer_index_from_bases::<N, 8, _>(bases()).collect::<Vec<_>>(),
+            lmers
+        );
+        assert_eq!(
+            KT::iter_lmer_index_from_bases::<N, 20, _>(bases()).collect::<Vec<_>>(),
+            lmers
+        );
+        assert_eq!(
+            KT::iter_lmer_index_from_bases::<N, N, _>(bases()).collect::<Vec<_>>(),
+            lmers
+        );
+        let stranded: Vec<_> = KT::iter_lmer_index_from_bases::<N, 8, _>(bases())
+            .with_strand()
+            .collect();
+        assert_eq!(stranded.len(), kmers.len());
+        for (kmer, &(lmer, idx, strand)) in kmers.iter().zip(stranded.iter()) {
+            assert_eq!((lmer, idx, strand), kmer.lmer_index_with_strand());
+            assert_eq!(KT::from_lmer_index_with_strand(lmer, idx, strand), *kmer);
+        }
+    }
+
+    #[test]
+    fn test_lmer_iter_one_base() {
+        type KT = RawKmer<1, u8>;
+        let nucs = b"ACGTTGCAAGGCCTTA";
+        let kmers: Vec<_> = KT::iter_from_nucs(nucs.iter()).collect();
+        let stranded: Vec<_> = KT::iter_lmer_index_from_nucs::<1, 1, _>(nucs.iter())
+            .with_strand()
+            .collect();
+        assert_eq!(stranded.len(), nucs.len());
+        for (kmer, &(lmer, idx, strand)) in kmers.iter().zip(stranded.iter()) {
+            assert_eq!((lmer, idx, strand), kmer.lmer_index_with_strand());
+        }
+    }
+
+    #[test]
+    fn test_lmer_iter_periodic() {
+        // K = 5, the 9-bit words 011011011 and 001001001 have period 3
+        let nucs = b"ACGTACGTACGGGGGGGGGGAAAAAATTTTTCCCCC";
+        let lmers: Vec<_> = RawKmer::<5, u16>::iter_from_nucs(nucs.iter())
+            .map(|kmer| kmer.lmer_index())
+            .collect();
+        let lmers_iter: Vec<_> =
+            RawKmer::<5, u16>::iter_lmer_index_from_nucs::<9, 4, _>(nucs.iter()).collect();
+        assert_eq!(lmers_iter, lmers);
+    }
+
+    #[test]
+    fn test_fast_necklace() {
+        for n in [1, 2, 6, 9, 12, 15] {
+            for x in 0..(1u32 << n) {
+                assert_eq!(fast_necklace_n(x, n), necklace_n(x, n));
+                assert_eq!(fast_necklace_index_n(x, n), necklace_index_n(x, n));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_necklace_wide() {
+        use crate::utils::random_kmers;
+        use crate::wide::WideUint;
+        type T = WideUint<3>;
+        const K: usize = 81;
+        for kmer in random_kmers::<K, T, RawKmer<K, T>>(1000) {
+            let x = kmer.to_int() >> 1;
+            assert_eq!(fast_necklace_index::<K, T>(x), necklace_index::<K, T>(x));
+        }
+        // word of period 7
+        let x = (0..23).fold(T::ZERO, |x, _| (x << 7) | T::from_u64(0b1101001));
+        assert_eq!(fast_necklace_index::<K, T>(x), necklace_index::<K, T>(x));
+        assert_eq!(period_n(fast_necklace::<K, T>(x), 2 * K - 1), 7);
+    }
+
+    #[test]
+    fn test_necklaces() {
+        for n in 1..=14 {
+            let necklaces: Vec<u32> = (0..(1 << n)).filter(|&x| necklace_n(x, n) == x).collect();
+            assert_eq!(necklaces_n::<u32>(n).collect::<Vec<_>>(), necklaces);
+            let lyndon_words: Vec<u32> = necklaces
+                .iter()
+                .copied()
+                .filter(|&x| period_n(x, n) == n)
+                .collect();
+            assert_eq!(lyndon_words_n::<u32>(n).collect::<Vec<_>>(), lyndon_words);
+        }
+        assert_eq!(next_necklace_n(0b0011u8, 4), Some(0b0101));
+        assert_eq!(next_necklace_n(0b0111u8, 4), Some(0b1111));
+        assert_eq!(next_necklace_n(0b1111u8, 4), None);
+        assert_eq!(next_lyndon_word_n(0b0011u8, 4), Some(0b0111));
+    }
+
+    #[test]
+    fn test_necklaces_long() {
+        use crate::rank::{num_lyndon_words, num_necklaces, Ranker};
+        assert_eq!(necklaces_n::<u32>(20).count() as u128, num_necklaces(20));
+        assert_eq!(
+            lyndon_words_n::<u32>(20).count() as u128,
+            num_lyndon_words(20)
+        );
+        const N: usize = 125;
+        let ranker = Ranker::<N, u128>::new();
+        for (r, x) in necklaces_n::<u128>(N).take(1000).enumerate() {
+            assert_eq!(ranker.rank(x), r as u128);
+        }
+    }
+
+    #[test]
+    fn test_period() {
+        use crate::rank::num_lyndon_words;
+        const K: usize = 8;
+        const N: usize = canon_bits(K);
+        let mut lyndon_words = 0;
+        for x in necklaces_n::<u32>(N) {
+            let rotations: BTreeSet<_> = (0..N).map(|i| rot_left_n(x, N, i)).collect();
+            assert_eq!(period::<K, u32>(x), rotations.len());
+            if is_lyndon::<K, u32>(x) {
+                lyndon_words += 1;
+            }
+        }
+        assert_eq!(lyndon_words, num_lyndon_words(N));
+        assert!(!is_lyndon::<K, u16>(0b110000000000000));
+        assert!(!is_lyndon_n(0b0101u8, 4));
+        assert!(is_lyndon_n(0b0011u8, 4));
+    }
+
+    #[test]
+    fn test_canonical_index() {
+        for i in (0..(1 << 18)).step_by(3) {
+            let kmer = RawKmer::<9, u32>::from_int(i);
+            let (lmer, idx) = kmer.lmer_index();
+            let p = period::<9, u32>(lmer);
+            assert!(idx < p);
+            for j in 0..(17 / p) {
+                let other = idx + j * p;
+                assert_eq!(canonical_index::<9, u32>(lmer, other), idx);
+                let kmer2 = RawKmer::<9, u32>::from_lmer_index(lmer, other);
+                assert_eq!(kmer2, kmer.canonical());
+            }
+        }
+    }
+
+    #[test]
+    fn test_stranded_lmer() {
+        use crate::rank::{num_necklaces, Ranker};
+        const K: usize = 5;
+        type KT = RawKmer<K, u16>;
+        let ranker = Ranker::<{ 2 * K }, u16>::new();
+        let mut lmers = BTreeSet::new();
+        for i in 0..(1 << (2 * K)) {
+            let kmer = KT::from_int(i);
+            let (lmer, idx) = kmer.stranded_lmer_index();
+            assert_eq!(lmer, kmer.stranded_lmer());
+            assert_eq!(lmer, necklace_n(i, 2 * K));
+            assert_eq!(KT::from_stranded_lmer_index(lmer, idx), kmer);
+            assert_eq!(ranker.try_rank(lmer), Ok(ranker.rank(lmer)));
+            lmers.insert(lmer);
+        }
+        assert_eq!(lmers.len() as u128, num_necklaces(2 * K));
+        // ACGTT and AACGT have the same canonical lmer but not the same stranded one
+        let kmer = KT::from_nucs(b"ACGTT");
+        assert_eq!(kmer.lmer(), kmer.rev_comp().lmer());
+        assert_ne!(kmer.stranded_lmer(), kmer.rev_comp().stranded_lmer());
+    }
+
+    #[test]
+    fn test_expand_lmer() {
+        use std::collections::BTreeMap;
+        const K: usize = 7;
+        type KT = RawKmer<K, u16>;
+        let mut buckets = BTreeMap::<_, BTreeSet<_>>::new();
+        for i in 0..(1 << (2 * K)) {
+            let kmer = KT::from_int(i).canonical();
+            buckets
+                .entry(kmer.lmer())
+                .or_default()
+                .insert(kmer.to_int());
+        }
+        for (lmer, kmers) in buckets {
+            let expanded: Vec<_> = KT::expand_lmer(lmer).map(|kmer| kmer.to_int()).collect();
+            assert_eq!(expanded.len(), kmers.len());
+            assert_eq!(expanded.into_iter().collect::<BTreeSet<_>>(), kmers);
+        }
+    }
+
+    #[test]
+    fn test_lmer_even() {
+        use std::collections::BTreeMap;
+        const K: usize = 6;
+        const N: usize = canon_bits(K);
+        type KT = RawKmer<K, u16>;
+        let mut buckets = BTreeMap::<_, BTreeSet<_>>::new();
+        let mut palindromes = 0;
+        for i in 0..(1 << (2 * K)) {
+            let kmer = KT::from_int(i);
+            let canon = kmer.canonical();
+            assert_eq!(canon, kmer.min(kmer.rev_comp()));
+            assert_eq!(kmer.lmer(), kmer.rev_comp().lmer());
+            let (lmer, idx) = kmer.lmer_index();
+            assert_eq!(lmer, necklace_n(canon.to_int(), N));
+            assert_eq!(KT::from_lmer_index(lmer, idx), canon);
+            if kmer == kmer.rev_comp() {
+                palindromes += 1;
+            }
+            buckets.entry(lmer).or_default().insert(canon.to_int());
+        }
+        assert_eq!(palindromes, 1 << K);
+        for (lmer, kmers) in buckets {
+            let expanded: BTreeSet<_> = KT::expand_lmer(lmer).map(|kmer| kmer.to_int()).collect();
+            assert_eq!(expanded, kmers);
+        }
+    }
+
+    #[test]
+    fn test_lmer_iter_even() {
+        use crate::utils::random_kmers;
+        const K: usize = 16;
+        const N: usize = canon_bits(K);
+        type KT = RawKmer<K, u64>;
+        let kmers = random_kmers::<K, u64, KT>(10_000);
+        let bases: Vec<_> = kmers[0]
+            .to_bases()
+            .into_iter()
+            .chain(kmers[1..].iter().map(|kmer| kmer.to_int() & 0b11))
+            .collect();
+        let lmers: Vec<_> = kmers.iter().map(|kmer| kmer.lmer_index()).collect();
+        let bases = || bases.iter().copied();
+        assert_eq!(
+            KT::iter_lmer_index_from_bases::<N, 8, _>(bases()).collect::<Vec<_>>(),
+            lmers
+        );
+        let stranded = KT::iter_lmer_index_from_bases::<N, 8, _>(bases()).with_strand();
+        for (kmer, (lmer, idx, strand)) in kmers.iter().zip(stranded) {
+            assert_eq!(strand, kmer.canonical_with_strand().1);
+            assert_eq!((lmer, idx, strand), kmer.lmer_index_with_strand());
+        }
+        let ranker = Ranker::<N, u64>::new();
+        for kmer in kmers.into_iter().take(100) {
+            let code = kmer.dense_code(&ranker);
+            assert!(code < 1 << (2 * K));
+            assert_eq!(KT::from_dense_code(code, &ranker), kmer.canonical());
+        }
+    }
+
+    #[test]
+    fn test_dense_code() {
+        use crate::utils::random_kmers;
+        const K: usize = 31;
+        let ranker = Ranker::<{ 2 * K - 1 }, u64>::new();
+        for kmer in random_kmers::<K, u64, RawKmer<K, u64>>(100) {
+            let code = kmer.dense_code(&ranker);
+            assert!(code < 1 << (2 * K - 1));
+            assert_eq!(
+                RawKmer::<K, u64>::from_dense_code(code, &ranker),
+                kmer.canonical()
+            );
+        }
+    }
+
+    #[test]
+    fn test_bin_rot_left() {
+        for i in 0..(1 << 13) {
+            let x = bin_rot_right::<7, u32>(i);
+            assert_eq!(bin_rot_left::<7, u32>(x), i);
+        }
+    }
+
+    #[test]
+    fn test_from_lmer_index() {
+        for i in 0..(1 << 18) {
+            let kmer = RawKmer::<9, u32>::from_int(i);
+            let (lmer, idx) = kmer.lmer_index();
+            assert_eq!(
+                RawKmer::<9, u32>::from_lmer_index(lmer, idx),
+                kmer.canonical()
+            );
+        }
+    }
 }
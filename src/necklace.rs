@@ -1,3 +1,4 @@
+use crate::utils::low_mask;
 use core::cmp::min;
 use num_traits::int::PrimInt;
 use std::collections::VecDeque;
@@ -102,72 +103,170 @@ pub struct NecklaceQueue<const N: usize, const W: usize, T: PrimInt> {
     min_queue: LexMinQueue<W, T>,
 }
 
-macro_rules! impl_t {
-($($T:ty),+) => {$(
-impl<const N: usize, const W: usize> NecklaceQueue<N, W, $T> {
+impl<const N: usize, const W: usize, T: PrimInt> NecklaceQueue<N, W, T> {
     const M: usize = N - W + 1;
-    const MASK: $T = (1 << N) - 1;
-    const MIN_MASK: $T = (1 << Self::M) - 1;
 
     pub fn new() -> Self {
         Self {
-            word: 0,
+            word: T::zero(),
             min_queue: LexMinQueue::new(),
         }
     }
 
-    pub fn new_from_word(word: $T) -> Self {
+    pub fn new_from_word(word: T) -> Self {
         let mut res = Self::new();
         res.insert_full(word);
         res
     }
 
     #[inline]
-    fn rotation(&self, p: usize) -> $T {
-        ((self.word << p) & Self::MASK) | (self.word >> (N - p))
-    }
-
-    pub fn get_necklace_pos(&self) -> ($T, usize) {
-        min(
-            self.min_queue
-                .iter_min_pos()
-                .map(|p| (self.rotation(p), p))
-                .min()
-                .unwrap(),
-            (W..N)
-                .map(|p| (self.rotation(p), p))
-                .min()
-                .unwrap(),
-        )
-    }
-
-    pub fn insert_full(&mut self, word: $T) {
-        self.word = word & Self::MASK;
-        let vals = (0..W).map(|p|
-            (word >> (N - p - Self::M)) & Self::MIN_MASK
-        );
+    fn mask() -> T {
+        low_mask(N)
+    }
+
+    #[inline]
+    fn min_mask() -> T {
+        low_mask(Self::M)
+    }
+
+    #[inline]
+    fn rotation(&self, p: usize) -> T {
+        if p == 0 {
+            self.word
+        } else {
+            ((self.word << p) & Self::mask()) | (self.word >> (N - p))
+        }
+    }
+
+    pub fn get_necklace_pos(&self) -> (T, usize) {
+        self.min_queue
+            .iter_min_pos()
+            .chain(W..N)
+            .map(|p| (self.rotation(p), p))
+            .min()
+            .unwrap()
+    }
+
+    pub fn insert_full(&mut self, word: T) {
+        self.word = word & Self::mask();
+        let vals = (0..W).map(|p| (word >> (N - p - Self::M)) & Self::min_mask());
         self.min_queue.insert_full(vals);
     }
 
-    pub fn insert(&mut self, x: $T) {
-        self.word = ((self.word << 1) & Self::MASK) | (x & 0b1);
-        self.min_queue.insert(self.word & Self::MIN_MASK);
+    pub fn insert(&mut self, x: T) {
+        self.word = ((self.word << 1) & Self::mask()) | (x & T::one());
+        self.min_queue.insert(self.word & Self::min_mask());
     }
 
-    pub fn insert2(&mut self, x: $T) {
-        self.word = ((self.word << 2) & Self::MASK) | (x & 0b11);
-        self.min_queue.insert2((self.word >> 1) & Self::MIN_MASK, self.word & Self::MIN_MASK);
+    pub fn insert2(&mut self, x: T) {
+        if N < 2 {
+            // only the last bit is kept
+            return self.insert_full(x & T::one());
+        }
+        self.word = ((self.word << 2) & Self::mask()) | (x & T::from(0b11).unwrap());
+        self.min_queue.insert2(
+            (self.word >> 1) & Self::min_mask(),
+            self.word & Self::min_mask(),
+        );
     }
 }
 
-impl<const N: usize, const W: usize> Default for NecklaceQueue<N, W, $T> {
+impl<const N: usize, const W: usize, T: PrimInt> Default for NecklaceQueue<N, W, T> {
     fn default() -> Self {
-    Self::new()
+        Self::new()
     }
 }
-)*}}
 
-impl_t!(u8, u16, u32, u64, u128);
+/// Same as `NecklaceQueue` for a word whose bits are inserted at the front,
+/// e.g. the reverse complement of a k-mer read forward: the newest windows
+/// are the first rotations, so positions are mirrored in the `LexMinQueue`.
+#[derive(Debug)]
+pub struct FrontNecklaceQueue<const N: usize, const W: usize, T: PrimInt> {
+    word: T,
+    min_queue: LexMinQueue<W, T>,
+}
+
+impl<const N: usize, const W: usize, T: PrimInt> FrontNecklaceQueue<N, W, T> {
+    const M: usize = N - W + 1;
+
+    pub fn new() -> Self {
+        Self {
+            word: T::zero(),
+            min_queue: LexMinQueue::new(),
+        }
+    }
+
+    pub fn new_from_word(word: T) -> Self {
+        let mut res = Self::new();
+        res.insert_full(word);
+        res
+    }
+
+    #[inline]
+    fn mask() -> T {
+        low_mask(N)
+    }
+
+    #[inline]
+    fn min_mask() -> T {
+        low_mask(Self::M)
+    }
+
+    #[inline]
+    fn rotation(&self, p: usize) -> T {
+        if p == 0 {
+            self.word
+        } else {
+            ((self.word << p) & Self::mask()) | (self.word >> (N - p))
+        }
+    }
+
+    #[inline]
+    fn window(&self, p: usize) -> T {
+        (self.word >> (N - p - Self::M)) & Self::min_mask()
+    }
+
+    pub fn get_necklace_pos(&self) -> (T, usize) {
+        self.min_queue
+            .iter_min_pos()
+            .map(|p| W - 1 - p)
+            .chain(W..N)
+            .map(|p| (self.rotation(p), p))
+            .min()
+            .unwrap()
+    }
+
+    pub fn insert_full(&mut self, word: T) {
+        self.word = word & Self::mask();
+        // the first window is the newest one
+        let vals = (0..W)
+            .rev()
+            .map(|p| (word >> (N - p - Self::M)) & Self::min_mask());
+        self.min_queue.insert_full(vals);
+    }
+
+    pub fn insert(&mut self, x: T) {
+        self.word = (self.word >> 1) | ((x & T::one()) << (N - 1));
+        self.min_queue.insert(self.window(0));
+    }
+
+    pub fn insert2(&mut self, x: T) {
+        if N < 2 {
+            // only the first bit is kept
+            return self.insert_full((x >> 1) & T::one());
+        }
+        self.word = (self.word >> 2) | ((x & T::from(0b11).unwrap()) << (N - 2));
+        let v = self.window(0);
+        let u = if W > 1 { self.window(1) } else { v };
+        self.min_queue.insert2(u, v);
+    }
+}
+
+impl<const N: usize, const W: usize, T: PrimInt> Default for FrontNecklaceQueue<N, W, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -237,6 +336,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_front_necklace_queue() {
+        use crate::lyndon::fast_necklace_n;
+        const N: usize = 13;
+        let mut word = 0b1011001110001u32;
+        let mut front_queue = FrontNecklaceQueue::<N, 4, u32>::new_from_word(word);
+        for i in 0..200u32 {
+            let x = (i * 7 + i / 3) & 0b11;
+            word = (word >> 2) | (x << (N - 2));
+            front_queue.insert2(x);
+            let (neck, p) = front_queue.get_necklace_pos();
+            assert_eq!(neck, fast_necklace_n(word, N));
+            assert_eq!(neck, ((word << p) & 0x1fff) | (word >> (N - p)));
+        }
+    }
+
     #[test]
     fn test_necklace_queue() {
         let mut necklace_queue = NecklaceQueue::<N, W, u64>::new_from_word(0b10010110);
@@ -244,4 +359,16 @@ mod tests {
         necklace_queue.insert(0);
         assert_eq!(necklace_queue.get_necklace_pos(), (0b00001011, N - 2));
     }
+
+    #[test]
+    fn test_necklace_queue_one_bit() {
+        let mut queue = NecklaceQueue::<1, 1, u8>::new_from_word(1);
+        let mut front_queue = FrontNecklaceQueue::<1, 1, u8>::new_from_word(1);
+        for x in [0b00, 0b01, 0b10, 0b11] {
+            queue.insert2(x);
+            front_queue.insert2(x);
+            assert_eq!(queue.get_necklace_pos(), (x & 1, 0));
+            assert_eq!(front_queue.get_necklace_pos(), (x >> 1, 0));
+        }
+    }
 }
@@ -0,0 +1,482 @@
+// Necklaces over the 4-letter alphabet of bases, where k-mers are rotated
+// by whole bases instead of single bits of the canonical word.
+//
+// Quaternary lmers are the smallest necklace of a k-mer and its reverse complement,
+// but they are ranked among all the necklaces of K bases: about half of the ranks
+// are never used, so a code spends one more bit than the number of lmers needs.
+// With the rotation and the strand, codes take 2K + 2 bits.
+
+use crate::kmer::{Base, Kmer, Strand};
+use crate::rank::{phi, RankError};
+use crate::utils::low_mask;
+use core::cmp::min;
+use num_traits::int::PrimInt;
+
+const SIGMA: usize = 4;
+
+/// Rotate the `k`-base word `x` `p` bases to the left
+#[inline]
+fn base_rot_left_n<T: Base>(x: T, k: usize, p: usize) -> T {
+    if p == 0 {
+        x
+    } else {
+        ((x << (2 * p)) & low_mask(2 * k)) | (x >> (2 * (k - p)))
+    }
+}
+
+/// Smallest left rotation by bases of the `k`-base word `x` reaching its necklace,
+/// using the same two-pointer scheme as `lyndon::least_rotation_n`
+pub fn least_base_rotation_n<T: Base>(x: T, k: usize) -> usize {
+    let pad = T::zero().count_zeros() as usize - 2 * k;
+    let (mut i, mut j) = (0, 1);
+    while i < k && j < k {
+        let (u, v) = (base_rot_left_n(x, k, i), base_rot_left_n(x, k, j));
+        if u == v {
+            break;
+        }
+        let lcp = ((u ^ v).leading_zeros() as usize - pad) / 2;
+        if u > v {
+            i += lcp + 1;
+        } else {
+            j += lcp + 1;
+        }
+        if i == j {
+            j += 1;
+        }
+    }
+    min(i, j)
+}
+
+/// Smallest period in bases of the `k`-base word `x`
+pub fn base_period_n<T: Base>(x: T, k: usize) -> usize {
+    (1..k)
+        .filter(|&d| k.is_multiple_of(d))
+        .find(|&d| base_rot_left_n(x, k, d) == x)
+        .unwrap_or(k)
+}
+
+/// Smallest rotation by bases of the `k`-base word `x`
+#[inline]
+pub fn base_necklace_n<T: Base>(x: T, k: usize) -> T {
+    base_rot_left_n(x, k, least_base_rotation_n(x, k))
+}
+
+/// Smallest rotation by bases of the `k`-base word `x`
+/// with the smallest number of right rotations by bases to reach it
+pub fn base_necklace_index_n<T: Base>(x: T, k: usize) -> (T, usize) {
+    let p = least_base_rotation_n(x, k);
+    let res = base_rot_left_n(x, k, p);
+    (res, (k - p) % base_period_n(res, k))
+}
+
+/// Next `k`-base prenecklace after the prenecklace `w` in lex order with its period,
+/// same as `lyndon::next_prenecklace_n` where the last base below G is incremented
+pub(crate) fn next_base_prenecklace_n<T: Base>(w: T, k: usize) -> Option<(T, usize)> {
+    let t = (!w).trailing_zeros() as usize / 2;
+    if t >= k {
+        return None;
+    }
+    let p = k - t;
+    let mut res = ((w >> (2 * t)) + T::one()) << (2 * t);
+    let mut len = p;
+    while len < k {
+        res = res | (res >> (2 * len));
+        len *= 2;
+    }
+    Some((res, p))
+}
+
+/// Lmers made of necklaces over bases, canonicalized by reverse complement.
+/// Their codes take 2K + 2 bits, two more than the k-mer itself, so compression
+/// comparisons against binary lmers should count the code bits, not the lmer bits.
+pub trait QuaternaryLyndon<const K: usize, T: Base>: Kmer<K, T> {
+    /// Smallest necklace over bases of the k-mer and its reverse complement
+    fn quaternary_lmer(self) -> T {
+        let lmer = base_necklace_n(self.to_int(), K);
+        let rc_lmer = base_necklace_n(self.rev_comp().to_int(), K);
+        min(lmer, rc_lmer)
+    }
+    /// Same as `quaternary_lmer` with the number of right rotations
+    /// by bases to reach it, and the strand it was read on
+    fn quaternary_lmer_index(self) -> (T, usize, Strand) {
+        let (lmer, idx) = base_necklace_index_n(self.to_int(), K);
+        let (rc_lmer, rc_idx) = base_necklace_index_n(self.rev_comp().to_int(), K);
+        if lmer <= rc_lmer {
+            (lmer, idx, Strand::Forward)
+        } else {
+            (rc_lmer, rc_idx, Strand::Reverse)
+        }
+    }
+    /// Recover the k-mer from the output of `quaternary_lmer_index`
+    fn from_quaternary_lmer_index(lmer: T, idx: usize, strand: Strand) -> Self {
+        let kmer = Self::from_int(base_rot_left_n(lmer, K, idx % K));
+        match strand {
+            Strand::Forward => kmer,
+            Strand::Reverse => kmer.rev_comp(),
+        }
+    }
+    /// Code of the k-mer in `[0, 2^(2K+2))` made of the rank of its quaternary lmer,
+    /// the rotation and the strand
+    #[inline]
+    fn quaternary_code(self, ranker: &QuaternaryRanker<K, T>) -> T {
+        let (lmer, idx, strand) = self.quaternary_lmer_index();
+        ranker.encode(lmer, idx, strand)
+    }
+    /// Recover the k-mer from the output of `quaternary_code`
+    #[inline]
+    fn from_quaternary_code(code: T, ranker: &QuaternaryRanker<K, T>) -> Self {
+        let (lmer, idx, strand) = ranker.decode(code);
+        Self::from_quaternary_lmer_index(lmer, idx, strand)
+    }
+}
+
+impl<const K: usize, T: Base, KT: Kmer<K, T>> QuaternaryLyndon<K, T> for KT {}
+
+/// Number of necklaces of length n over bases, for 1 ≤ n ≤ 63
+pub fn num_quaternary_necklaces(n: usize) -> u128 {
+    assert!((1..64).contains(&n), "n must be in 1..64");
+    (1..=n)
+        .filter(|&d| n.is_multiple_of(d))
+        .map(|d| (phi(n / d) as u128) << (2 * d))
+        .sum::<u128>()
+        / n as u128
+}
+
+pub struct QuaternaryRanker<const N: usize, T> {
+    inner: DynQuaternaryRanker<T>,
+}
+
+impl<const N: usize, T: PrimInt> Default for QuaternaryRanker<N, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, T: PrimInt> QuaternaryRanker<N, T> {
+    /// Panics if `T` cannot rank necklaces of N bases
+    pub fn new() -> Self {
+        Self {
+            inner: DynQuaternaryRanker::new(N),
+        }
+    }
+
+    pub fn try_new() -> Result<Self, RankError> {
+        Ok(Self {
+            inner: DynQuaternaryRanker::try_new(N)?,
+        })
+    }
+
+    /// Rank of w
+    #[inline]
+    pub fn rank(&self, w: T) -> T {
+        self.inner.rank(w)
+    }
+
+    /// Necklace of rank r
+    #[inline]
+    pub fn unrank(&self, r: T) -> T {
+        self.inner.unrank(r)
+    }
+
+    /// Code of the rotation `idx` of the necklace `lmer` read on `strand`
+    #[inline]
+    pub fn encode(&self, lmer: T, idx: usize, strand: Strand) -> T {
+        self.inner.encode(lmer, idx, strand)
+    }
+
+    /// Necklace, rotation index and strand of a code
+    #[inline]
+    pub fn decode(&self, code: T) -> (T, usize, Strand) {
+        self.inner.decode(code)
+    }
+}
+
+/// Ranker for necklaces of `n` bases, with `n` chosen at runtime.
+/// It generalizes the binary ranker with Burnside's lemma: for each period `d`
+/// dividing `n`, the words of length `d` whose rotations are all greater than
+/// the necklace are counted as closed walks in its KMP automaton.
+/// Counting takes O(n^2) operations, and unranking O(n^3).
+pub struct DynQuaternaryRanker<T> {
+    n: usize,
+    rot_bits: usize,
+    divs: Vec<usize>,
+    phis: Vec<T>,
+}
+
+impl<T: PrimInt> DynQuaternaryRanker<T> {
+    /// Panics if `T` cannot rank necklaces of n bases
+    pub fn new(n: usize) -> Self {
+        match Self::try_new(n) {
+            Ok(ranker) => ranker,
+            Err(_) => panic!(
+                "necklaces of {n} bases need more than {} bits",
+                T::zero().count_zeros()
+            ),
+        }
+    }
+
+    /// Ranking sums up to n times the number of necklaces, which is less than 2^(2n+1),
+    /// and codes take 2n + 2 bits
+    pub fn try_new(n: usize) -> Result<Self, RankError> {
+        if n == 0 || 2 * n + 2 > T::zero().count_zeros() as usize {
+            return Err(RankError::Overflow);
+        }
+        let rot_bits = n.next_power_of_two().ilog2() as usize;
+        let divs: Vec<_> = (1..=n).filter(|&d| n.is_multiple_of(d)).collect();
+        let phis = divs.iter().map(|&d| T::from(phi(n / d)).unwrap()).collect();
+        Ok(Self {
+            n,
+            rot_bits,
+            divs,
+            phis,
+        })
+    }
+
+    #[inline]
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    fn to_digits(&self, w: T) -> Vec<usize> {
+        (0..self.n)
+            .map(|i| {
+                ((w >> (2 * (self.n - i - 1))) & T::from(3).unwrap())
+                    .to_usize()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn from_digits(a: &[usize]) -> T {
+        a.iter()
+            .fold(T::zero(), |w, &c| (w << 2) | T::from(c).unwrap())
+    }
+
+    fn lyn_necklace(a: &[usize]) -> (usize, bool) {
+        let mut p = 1;
+        for i in 1..a.len() {
+            if a[i] < a[i - p] {
+                return (p, false);
+            } else if a[i] > a[i - p] {
+                p = i + 1;
+            }
+        }
+        (p, true)
+    }
+
+    /// Compute largest necklace <= a
+    fn largest_necklace(a: &mut [usize]) {
+        let n = a.len();
+        let (mut p, mut done) = Self::lyn_necklace(a);
+        // a prenecklace is a necklace iff its period divides n
+        while !done || !n.is_multiple_of(p) {
+            a[p - 1] -= 1;
+            a[p..].fill(SIGMA - 1);
+            (p, done) = Self::lyn_necklace(a);
+        }
+    }
+
+    /// Transitions of the KMP automaton of the necklace a, where state m means
+    /// that a[..m] is the longest pending match, and None that a rotation is <= a
+    fn automaton(a: &[usize]) -> Vec<[Option<usize>; SIGMA]> {
+        let n = a.len();
+        let mut fail = vec![0; n];
+        for m in 1..n {
+            let mut l = fail[m - 1];
+            while l > 0 && a[m] != a[l] {
+                l = fail[l - 1];
+            }
+            fail[m] = if a[m] == a[l] { l + 1 } else { 0 };
+        }
+        (0..n)
+            .map(|m| {
+                // pending matches are m and its borders
+                let mut borders = vec![m];
+                while let Some(&l) = borders.last().filter(|&&l| l > 0) {
+                    borders.push(fail[l - 1]);
+                }
+                core::array::from_fn(|c| {
+                    if borders.iter().any(|&l| c < a[l]) {
+                        return None;
+                    }
+                    let next = borders.iter().find(|&&l| a[l] == c).map_or(0, |&l| l + 1);
+                    (next < n).then_some(next)
+                })
+            })
+            .collect()
+    }
+
+    /// Number of necklaces <= w
+    fn count(&self, w: T) -> T {
+        let n = self.n;
+        let mut a = self.to_digits(w);
+        Self::largest_necklace(&mut a);
+        let delta = Self::automaton(&a);
+        // borders of a prenecklace are followed by smaller bases, so states only grow
+        // by one or fall back to 0: the closed walks of length d are the walks
+        // from 0 to 0 of length d - j, with a marked first return of length j
+        let (mut back, mut first) = (vec![T::zero(); n + 1], vec![T::zero(); n + 1]);
+        let (mut cur, mut first_cur) = (vec![T::zero(); n], vec![T::zero(); n]);
+        let mut next = vec![T::zero(); n];
+        cur[0] = T::one();
+        first_cur[0] = T::one();
+        back[0] = T::one();
+        for l in 1..=n {
+            for (vals, res) in [(&mut cur, &mut back), (&mut first_cur, &mut first)] {
+                next.fill(T::zero());
+                for (s, &x) in vals.iter().enumerate() {
+                    if x != T::zero() {
+                        for &t in delta[s].iter().flatten() {
+                            next[t] = next[t] + x;
+                        }
+                    }
+                }
+                core::mem::swap(vals, &mut next);
+                res[l] = vals[0];
+            }
+            first_cur[0] = T::zero();
+        }
+        let walks = |d: usize| {
+            (1..=d).fold(T::zero(), |acc, j| {
+                acc + T::from(j).unwrap() * first[j] * back[d - j]
+            })
+        };
+        let mut r = T::zero();
+        for (&d, &phi) in self.divs.iter().zip(self.phis.iter()) {
+            // words of length d with some rotation <= w
+            let words = (T::one() << (2 * d)) - walks(d);
+            r = r + phi * words;
+        }
+        r / T::from(n).unwrap()
+    }
+
+    /// Rank of w
+    pub fn rank(&self, w: T) -> T {
+        self.count(w) - T::one()
+    }
+
+    /// Necklace of rank r
+    pub fn unrank(&self, r: T) -> T {
+        let mut a = vec![0; self.n];
+        for i in 0..self.n {
+            a[i + 1..].fill(SIGMA - 1);
+            // fix a[i] to the first base c such that more than r necklaces are
+            // <= a[..i] c followed by the largest base G on the remaining positions
+            a[i] = (0..SIGMA - 1)
+                .find(|&c| {
+                    a[i] = c;
+                    self.count(Self::from_digits(&a)) > r
+                })
+                .unwrap_or(SIGMA - 1);
+        }
+        Self::from_digits(&a)
+    }
+
+    /// Code of the rotation `idx` of the necklace `lmer` read on `strand`,
+    /// with the rank in the high bits then `ceil(log2(n))` bits for the rotation
+    pub fn encode(&self, lmer: T, idx: usize, strand: Strand) -> T {
+        let code = (self.rank(lmer) << self.rot_bits) | T::from(idx).unwrap();
+        (code << 1) | T::from(strand as u8).unwrap()
+    }
+
+    /// Necklace, rotation index and strand of a code
+    pub fn decode(&self, code: T) -> (T, usize, Strand) {
+        let strand = if code & T::one() == T::zero() {
+            Strand::Forward
+        } else {
+            Strand::Reverse
+        };
+        let code = code >> 1;
+        let idx = (code & low_mask(self.rot_bits)).to_usize().unwrap();
+        (self.unrank(code >> self.rot_bits), idx, strand)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::{RawKmer, RevComp};
+
+    fn naive_base_necklace_index(x: u32, k: usize) -> (u32, usize) {
+        (0..k)
+            .map(|i| (base_rot_left_n(x, k, (k - i) % k), i))
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_base_necklace() {
+        for k in 1..=7 {
+            for x in 0..(1 << (2 * k)) {
+                assert_eq!(base_necklace_index_n(x, k), naive_base_necklace_index(x, k));
+                assert_eq!(base_necklace_n(x, k), naive_base_necklace_index(x, k).0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quaternary_lmer() {
+        const K: usize = 7;
+        type KT = RawKmer<K, u16>;
+        for i in 0..(1 << (2 * K)) {
+            let kmer = KT::from_int(i);
+            let (lmer, idx, strand) = kmer.quaternary_lmer_index();
+            assert_eq!(lmer, kmer.quaternary_lmer());
+            assert_eq!(lmer, kmer.rev_comp().quaternary_lmer());
+            assert_eq!(KT::from_quaternary_lmer_index(lmer, idx, strand), kmer);
+        }
+        let ranker = QuaternaryRanker::<K, u16>::new();
+        let mut codes = std::collections::HashSet::new();
+        for i in (0..(1 << (2 * K))).step_by(7) {
+            let kmer = KT::from_int(i);
+            let code = kmer.quaternary_code(&ranker);
+            assert!((code as usize) < 1 << (2 * K + 2));
+            assert!(codes.insert(code));
+            assert_eq!(KT::from_quaternary_code(code, &ranker), kmer);
+        }
+        // rotating by a base keeps the lmer
+        let kmer = KT::from_nucs(b"ACGTTGA");
+        assert_eq!(
+            KT::from_nucs(b"CGTTGAA").quaternary_lmer(),
+            kmer.quaternary_lmer()
+        );
+    }
+
+    #[test]
+    fn test_quaternary_rank() {
+        for n in 1..=7 {
+            let ranker = DynQuaternaryRanker::<u32>::new(n);
+            let mut r = 0;
+            for x in 0..(1 << (2 * n)) {
+                if base_necklace_n(x, n) == x {
+                    assert_eq!(ranker.rank(x), r, "n = {n}, x = {x:b}");
+                    assert_eq!(ranker.unrank(r), x, "n = {n}, r = {r}");
+                    r += 1;
+                } else {
+                    // rank of the largest necklace <= x
+                    assert_eq!(ranker.rank(x), r - 1, "n = {n}, x = {x:b}");
+                }
+            }
+            assert_eq!(r as u128, num_quaternary_necklaces(n));
+        }
+    }
+
+    #[test]
+    fn test_quaternary_rank_long() {
+        use crate::utils::random_kmers;
+        const K: usize = 31;
+        let ranker = QuaternaryRanker::<K, u64>::new();
+        let last = ranker.rank(u64::MAX >> 2);
+        assert_eq!(last as u128 + 1, num_quaternary_necklaces(K));
+        for kmer in random_kmers::<K, u64, RawKmer<K, u64>>(10) {
+            let lmer = kmer.quaternary_lmer();
+            assert_eq!(ranker.unrank(ranker.rank(lmer)), lmer);
+        }
+        assert!(QuaternaryRanker::<32, u64>::try_new().is_err());
+        // ranks fit in QUAT_LMER_BITS
+        for k in 1usize..64 {
+            let quat_rot_bits = k.next_power_of_two().ilog2() as usize;
+            assert!(num_quaternary_necklaces(k) <= 1 << (2 * k + 1 - quat_rot_bits));
+        }
+    }
+}
@@ -3,6 +3,13 @@
 // Adapted from http://www.cis.uoguelph.ca/~sawada/prog/ranking_necklaces.c
 // and optimized for a binary alphabet
 
+use crate::lyndon::fast_necklace_n;
+use core::fmt;
+use num_traits::int::PrimInt;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::sync::{Mutex, TryLockError};
+
 fn gcd(a: usize, b: usize) -> usize {
     let mut a = a;
     let mut b = b;
@@ -12,46 +19,275 @@ fn gcd(a: usize, b: usize) -> usize {
     a
 }
 
-fn phi(n: usize) -> usize {
+pub(crate) fn phi(n: usize) -> usize {
     (1..=n).filter(|&i| gcd(n, i) == 1).count()
 }
 
+fn mobius(n: usize) -> i8 {
+    let mut n = n;
+    let mut res = 1;
+    let mut p = 2;
+    while p * p <= n {
+        if n.is_multiple_of(p) {
+            n /= p;
+            if n.is_multiple_of(p) {
+                return 0;
+            }
+            res = -res;
+        }
+        p += 1;
+    }
+    if n > 1 {
+        res = -res;
+    }
+    res
+}
+
+/// Largest length whose number of binary necklaces is computed without overflow
+pub const MAX_COUNT_LEN: usize = 127;
+
+/// Number of binary necklaces of length n, for 1 ≤ n ≤ 127
+pub fn num_necklaces(n: usize) -> u128 {
+    assert!(
+        (1..=MAX_COUNT_LEN).contains(&n),
+        "n must be in 1..={MAX_COUNT_LEN}"
+    );
+    // the d = n term dominates, so the sum is less than 2^128
+    (1..=n)
+        .filter(|&d| n.is_multiple_of(d))
+        .map(|d| (phi(n / d) as u128) << d)
+        .fold(0u128, |s, x| s.checked_add(x).expect("overflow"))
+        / n as u128
+}
+
+/// Number of binary Lyndon words of length n, for 1 ≤ n ≤ 127
+pub fn num_lyndon_words(n: usize) -> u128 {
+    assert!(
+        (1..=MAX_COUNT_LEN).contains(&n),
+        "n must be in 1..={MAX_COUNT_LEN}"
+    );
+    let (mut pos, mut neg) = (0u128, 0u128);
+    for d in (1..=n).filter(|&d| n.is_multiple_of(d)) {
+        match mobius(d) {
+            1 => pos += 1 << (n / d),
+            -1 => neg += 1 << (n / d),
+            _ => (),
+        }
+    }
+    (pos - neg) / n as u128
+}
+
+/// Error returned by checked ranking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankError {
+    /// The word is not a necklace of length N
+    NotNecklace,
+    /// The integer type cannot hold the computations for this length
+    Overflow,
+}
+
+impl fmt::Display for RankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotNecklace => write!(f, "word is not a necklace of the ranker length"),
+            Self::Overflow => write!(f, "integer type is too small for the ranker length"),
+        }
+    }
+}
+
+impl Error for RankError {}
+
 pub struct Ranker<const N: usize, T> {
-    divs: Vec<usize>,
-    phis: Vec<T>,
+    inner: DynRanker<T>,
 }
 
-macro_rules! impl_rank {
-($($T:ty),+) => {$(
-impl<const N: usize> Default for Ranker<N, $T> {
+impl<const N: usize, T: PrimInt> Default for Ranker<N, T> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<const N: usize> Ranker<N, $T> {
+impl<const N: usize, T: PrimInt> Ranker<N, T> {
+    /// Panics if `T` cannot rank necklaces of length N
     pub fn new() -> Self {
+        Self {
+            inner: DynRanker::new(N),
+        }
+    }
+
+    pub fn try_new() -> Result<Self, RankError> {
+        Ok(Self {
+            inner: DynRanker::try_new(N)?,
+        })
+    }
+
+    /// Rank of w, which must be an N-bit word (see `try_rank`)
+    #[inline]
+    pub fn rank(&self, w: T) -> T {
+        self.inner.rank(w)
+    }
+
+    /// Rank of w, or an error if w is not a necklace of length N
+    #[inline]
+    pub fn try_rank(&self, w: T) -> Result<T, RankError> {
+        self.inner.try_rank(w)
+    }
+
+    /// Necklace of rank r
+    #[inline]
+    pub fn unrank(&self, r: T) -> T {
+        self.inner.unrank(r)
+    }
+
+    /// Ranks of all the necklaces of ws
+    #[inline]
+    pub fn rank_batch(&self, ws: &[T]) -> Vec<T> {
+        self.inner.rank_batch(ws)
+    }
+
+    /// Dense code of the rotation `idx` of the necklace `lmer`
+    #[inline]
+    pub fn encode(&self, lmer: T, idx: usize) -> T {
+        self.inner.encode(lmer, idx)
+    }
+
+    /// Necklace and rotation index of a dense code
+    #[inline]
+    pub fn decode(&self, code: T) -> (T, usize) {
+        self.inner.decode(code)
+    }
+
+    /// New workspace to share this ranker between threads with `rank_with`
+    #[inline]
+    pub fn workspace(&self) -> Workspace<T> {
+        self.inner.workspace()
+    }
+
+    /// Run f with the cached workspace, or with a new one if another thread holds it
+    #[inline]
+    pub fn with_workspace<R>(&self, f: impl FnOnce(&mut Workspace<T>) -> R) -> R {
+        self.inner.with_workspace(f)
+    }
+
+    /// Rank of w, using the given workspace
+    #[inline]
+    pub fn rank_with(&self, w: T, ws: &mut Workspace<T>) -> T {
+        self.inner.rank_with(w, ws)
+    }
+
+    /// Necklace of rank r, using the given workspace
+    #[inline]
+    pub fn unrank_with(&self, r: T, ws: &mut Workspace<T>) -> T {
+        self.inner.unrank_with(r, ws)
+    }
+
+    /// Dense code of the rotation `idx` of the necklace `lmer`, using the given workspace
+    #[inline]
+    pub fn encode_with(&self, lmer: T, idx: usize, ws: &mut Workspace<T>) -> T {
+        self.inner.encode_with(lmer, idx, ws)
+    }
+
+    /// Necklace and rotation index of a dense code, using the given workspace
+    #[inline]
+    pub fn decode_with(&self, code: T, ws: &mut Workspace<T>) -> (T, usize) {
+        self.inner.decode_with(code, ws)
+    }
+}
+
+/// Preallocated tables used to rank necklaces of length at most `n`
+#[derive(Debug, Clone)]
+pub struct Workspace<T> {
+    n: usize,
+    neck: Vec<usize>,
+    suf: Vec<usize>,
+    b: Vec<T>,
+}
+
+impl<T: PrimInt> Workspace<T> {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            neck: vec![0; n],
+            suf: vec![0; n * n],
+            b: vec![T::zero(); n * n],
+        }
+    }
+}
+
+/// Ranker for necklaces whose length `n` is chosen at runtime.
+/// The calls without a workspace reuse a cached one, unless another thread holds it.
+pub struct DynRanker<T> {
+    n: usize,
+    divs: Vec<usize>,
+    phis: Vec<T>,
+    n_t: T,
+    cache: Mutex<Workspace<T>>,
+}
+
+impl<T: PrimInt> DynRanker<T> {
+    /// Panics if `T` cannot rank necklaces of length n
+    pub fn new(n: usize) -> Self {
+        match Self::try_new(n) {
+            Ok(ranker) => ranker,
+            Err(_) => panic!(
+                "necklaces of length {n} need more than {} bits",
+                T::zero().count_zeros()
+            ),
+        }
+    }
+
+    /// Ranking sums up to n times the number of necklaces, which is less than 2^(n+1)
+    pub fn try_new(n: usize) -> Result<Self, RankError> {
+        if n == 0 || n >= T::zero().count_zeros() as usize {
+            return Err(RankError::Overflow);
+        }
         let mut divs = Vec::new();
         let mut phis = Vec::new();
-        for d in 1..=N {
-            if N % d == 0 {
+        for d in 1..=n {
+            if n.is_multiple_of(d) {
                 divs.push(d);
-                phis.push(phi(N / d) as $T);
+                phis.push(T::from(phi(n / d)).unwrap());
             }
         }
-        Self { divs, phis }
+        Ok(Self {
+            n,
+            divs,
+            phis,
+            n_t: T::from(n).unwrap(),
+            cache: Mutex::new(Workspace::new(n)),
+        })
     }
 
     #[inline]
-    fn get(w: $T, i: usize) -> $T {
-        (w >> (N - i - 1)) & 1
+    pub fn n(&self) -> usize {
+        self.n
     }
 
-    fn lyn_necklace(w: $T, n: usize) -> (usize, bool) {
+    #[inline]
+    pub fn workspace(&self) -> Workspace<T> {
+        Workspace::new(self.n)
+    }
+
+    /// Run f with the cached workspace, or with a new one if another thread holds it
+    pub fn with_workspace<R>(&self, f: impl FnOnce(&mut Workspace<T>) -> R) -> R {
+        match self.cache.try_lock() {
+            Ok(mut ws) => f(&mut ws),
+            // the tables are rebuilt on every call, so a poisoned workspace is still valid
+            Err(TryLockError::Poisoned(err)) => f(&mut err.into_inner()),
+            Err(TryLockError::WouldBlock) => f(&mut self.workspace()),
+        }
+    }
+
+    #[inline]
+    fn get(&self, w: T, i: usize) -> T {
+        (w >> (self.n - i - 1)) & T::one()
+    }
+
+    fn lyn_necklace(&self, w: T, n: usize) -> (usize, bool) {
         let mut p = 1;
         for i in 1..n {
-            let u = Self::get(w, i);
-            let v = Self::get(w, i - p);
+            let u = self.get(w, i);
+            let v = self.get(w, i - p);
             if u < v {
                 return (p, false);
             } else if u > v {
@@ -62,106 +298,335 @@ impl<const N: usize> Ranker<N, $T> {
     }
 
     /// Compute largest necklace <= w[..n]
-    fn largest_necklace(w: $T, n: usize) -> $T {
+    fn largest_necklace(&self, w: T, n: usize) -> (T, usize) {
         let mut res = w;
-        let (mut p, mut done) = Self::lyn_necklace(res, n);
-        while !done {
+        let (mut p, mut done) = self.lyn_necklace(res, n);
+        // a prenecklace is a necklace iff its period divides n
+        while !done || !n.is_multiple_of(p) {
             // res[p - 1] = 0;
-            res &= !(1 << (N - p));
+            res = res & !(T::one() << (self.n - p));
             // res[p..n].fill(1);
-            res |= (1 << (N - p)) - 1;
-            (p, done) = Self::lyn_necklace(res, n);
+            res = res | ((T::one() << (self.n - p)) - T::one());
+            (p, done) = self.lyn_necklace(res, n);
         }
-        res
+        (res, p)
     }
 
     /// Number of strings whose necklace is <= w
-    fn t(w: $T, n: usize) -> $T {
-        let mut suf = [[0; N]; N];
-        let mut b = [[0; N]; N];
+    #[allow(clippy::needless_range_loop)]
+    fn t(&self, w: T, n: usize, ws: &mut Workspace<T>) -> T {
+        debug_assert!(n <= ws.n, "workspace is too small");
+        let stride = ws.n;
+        let idx = |t: usize, j: usize| t * stride + j;
         let mut s;
-        let mut tot: $T;
 
         // Sets neck[..n] to the largest necklace less than or equal to w[..n]
-        let neck = Self::largest_necklace(w, n);
-        // let neck = w; // ???
+        let (neck_t, p) = self.largest_necklace(w, n);
+        let neck = &mut ws.neck[..n];
+        for (j, x) in neck.iter_mut().enumerate() {
+            *x = (self.get(neck_t, j) == T::one()) as usize;
+        }
+        let neck = &ws.neck[..n];
 
         // Compute b[t][j] = number of strings of length t with prefix neck[..j] but no suffix less than neck[..n]
-        // OPT: B[t][j] -> B[t-1][j], unroll 1st loop
+        let b = &mut ws.b;
         for t in 0..n {
-            b[t][t] = 1 - Self::get(neck, t) as $T; // 0 + X * 1
+            b[idx(t, t)] = T::from(1 - neck[t]).unwrap(); // 0 + X * 1
             for j in (0..t).rev() {
-                b[t][j] = b[t][j + 1] + (1 - Self::get(neck, j) as $T) * b[t - j - 1][0];
+                b[idx(t, j)] = if neck[j] == 0 {
+                    b[idx(t, j + 1)] + b[idx(t - j - 1, 0)]
+                } else {
+                    b[idx(t, j + 1)]
+                };
             }
         }
 
         // Compute suf[i][j] = longest suffix of neck[i..j] that is a prefix of neck[..n]
+        let suf = &mut ws.suf;
         for i in 1..n {
             s = i;
             for j in i..n {
-                if Self::get(neck, j) > Self::get(neck, j - s) {
+                if neck[j] > neck[j - s] {
                     s = j + 1;
                 }
-                suf[i][j] = j + 1 - s;
+                suf[idx(i, j)] = j + 1 - s;
             }
         }
 
         // Compute t
-        tot = Self::lyn_necklace(neck, n).0 as $T;
-        // OPT: B[t][j] -> B[t-1][j], unroll 1st loop
-        // for j in 0..n {
-        //     tot += Self::get(neck, j) << (n - 1 - j);
-        // }
-        tot += neck >> (N - n);
+        let mut tot = T::from(p).unwrap() + (neck_t >> (self.n - n));
         for t in 2..=n {
-            for j in 0..n {
-                if j + t <= n {
-                    tot += Self::get(neck, j) * b[t - 2][0] << (n - t - j);
+            // j + t <= n
+            let b0 = b[idx(t - 2, 0)];
+            for j in 0..=(n - t) {
+                if neck[j] == 1 {
+                    tot = tot + (b0 << (n - t - j));
+                }
+            }
+            // j = n - t + 1
+            for j in (n - t + 1)..n {
+                s = if j < n - t + 2 {
+                    0
                 } else {
-                    if j < n - t + 2 {
-                        s = 0;
-                    } else {
-                        s = suf[n - t + 1][j - 1];
-                    }
-                    if Self::get(neck, j) > Self::get(neck, s) {
-                        tot += b[n - j + s - 1][s + 1];
-                    }
+                    suf[idx(n - t + 1, j - 1)]
+                };
+                if neck[j] > neck[s] {
+                    tot = tot + b[idx(n - j + s - 1, s + 1)];
                 }
             }
-            // for j in 0..=(n - t) {
-            //     tot += Self::get(neck, j) * b[t - 2][0] << (n - t - j);
-            // }
-            // // j = n - t + 1
-            // s = 0;
-            // if Self::get(neck, n - t + 1) > Self::get(neck, s) {
-            //     tot += b[t - 1 + s - 1][s + 1];
-            // }
-            // for j in (n - t + 2)..n {
-            //     s = suf[n - t + 1][j - 1];
-            //     if Self::get(neck, j) > Self::get(neck, s) {
-            //         tot += b[n - j + s - 1][s + 1];
-            //     }
-            // }
         }
         tot
     }
 
-    /// Rank of w
-    pub fn rank(&self, w: $T) -> $T {
-        let mut r = 0;
-        for (&d, phi) in self.divs.iter().zip(self.phis.iter()) {
-            r += phi * Self::t(w, d);
+    /// Number of necklaces <= w
+    fn count(&self, w: T, ws: &mut Workspace<T>) -> T {
+        let mut r = T::zero();
+        for (&d, &phi) in self.divs.iter().zip(self.phis.iter()) {
+            r = r + phi * self.t(w, d, ws);
         }
-        r / (N as $T) - 1
+        r / self.n_t
+    }
+
+    /// Rank of w, which must be an n-bit word (unlike `try_rank`, it is not checked)
+    pub fn rank(&self, w: T) -> T {
+        self.with_workspace(|ws| self.rank_with(w, ws))
+    }
+
+    /// Rank of w, or an error if w is not a necklace of length n
+    pub fn try_rank(&self, w: T) -> Result<T, RankError> {
+        if !self.is_necklace(w) {
+            return Err(RankError::NotNecklace);
+        }
+        Ok(self.rank(w))
+    }
+
+    /// Check that w is a necklace of length n
+    pub fn is_necklace(&self, w: T) -> bool {
+        if w >> self.n != T::zero() {
+            return false;
+        }
+        // a prenecklace is a necklace iff its period divides n
+        let (p, done) = self.lyn_necklace(w, self.n);
+        done && self.n.is_multiple_of(p)
+    }
+
+    /// Necklace of rank r
+    pub fn unrank(&self, r: T) -> T {
+        self.with_workspace(|ws| self.unrank_with(r, ws))
+    }
+
+    /// Ranks of all the necklaces of ws
+    pub fn rank_batch(&self, ws: &[T]) -> Vec<T> {
+        self.with_workspace(|workspace| ws.iter().map(|&w| self.rank_with(w, workspace)).collect())
+    }
+
+    /// Dense code of the rotation `idx` of the necklace `lmer`
+    pub fn encode(&self, lmer: T, idx: usize) -> T {
+        self.with_workspace(|ws| self.encode_with(lmer, idx, ws))
+    }
+
+    /// Necklace and rotation index of a dense code
+    pub fn decode(&self, code: T) -> (T, usize) {
+        self.with_workspace(|ws| self.decode_with(code, ws))
+    }
+
+    /// Rank of w, using the given workspace
+    pub fn rank_with(&self, w: T, ws: &mut Workspace<T>) -> T {
+        self.count(w, ws) - T::one()
+    }
+
+    /// Necklace of rank r, using the given workspace
+    pub fn unrank_with(&self, r: T, ws: &mut Workspace<T>) -> T {
+        let mut w = T::zero();
+        for i in 0..self.n {
+            let bit = T::one() << (self.n - i - 1);
+            // fix w[i] to 1 if there are at most r necklaces with prefix w[..i]0
+            if self.count(w | (bit - T::one()), ws) <= r {
+                w = w | bit;
+            }
+        }
+        w
+    }
+
+    /// Dense code of the rotation `idx` of the necklace `lmer`, using the given workspace.
+    /// Rotations are numbered after the words whose necklace is smaller than `lmer`,
+    /// so that codes are ordered by necklace and cover exactly `[0, 2^n)`.
+    pub fn encode_with(&self, lmer: T, idx: usize, ws: &mut Workspace<T>) -> T {
+        let p = self.lyn_necklace(lmer, self.n).0;
+        self.t(lmer, self.n, ws) - T::from(p - idx % p).unwrap()
+    }
+
+    /// Necklace and rotation index of a dense code, using the given workspace
+    pub fn decode_with(&self, code: T, ws: &mut Workspace<T>) -> (T, usize) {
+        let mut w = T::zero();
+        for i in 0..self.n {
+            let bit = T::one() << (self.n - i - 1);
+            // fix w[i] to 1 if at most code words have a necklace <= w[..i]01..1
+            if self.t(w | (bit - T::one()), self.n, ws) <= code {
+                w = w | bit;
+            }
+        }
+        let p = self.lyn_necklace(w, self.n).0;
+        let start = self.t(w, self.n, ws) - T::from(p).unwrap();
+        (w, (code - start).to_usize().unwrap())
     }
 }
-)*}}
 
-impl_rank!(u8, u16, u32, u64, u128);
+/// Ranker backed by precomputed tables, for necklaces of length N ≤ 25 (K ≤ 13).
+/// Necklaces are marked in a bitvector of size 2^N with cumulative popcounts
+/// for `rank`, and stored in order for `unrank`.
+pub struct TableRanker<const N: usize, T> {
+    words: Vec<u64>,
+    blocks: Vec<u32>,
+    necklaces: Vec<T>,
+}
+
+impl<const N: usize, T: PrimInt> Default for TableRanker<N, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, T: PrimInt> TableRanker<N, T> {
+    pub const MAX_N: usize = 25;
+
+    /// Panics if N > MAX_N or if `T` cannot hold necklaces of length N
+    pub fn new() -> Self {
+        Self::try_new().expect("N must be ≤ MAX_N and fit in the integer type")
+    }
+
+    /// Necklaces are stored as `T`, which must hold N bits
+    pub fn try_new() -> Result<Self, RankError> {
+        Self::check_type()?;
+        let mut words = vec![0u64; (1usize << N).div_ceil(64)];
+        for x in 0..(1u32 << N) {
+            if fast_necklace_n(x, N) == x {
+                words[x as usize / 64] |= 1 << (x % 64);
+            }
+        }
+        Ok(Self::from_words(words))
+    }
+
+    fn check_type() -> Result<(), RankError> {
+        if N > Self::MAX_N || N > T::zero().count_zeros() as usize {
+            return Err(RankError::Overflow);
+        }
+        Ok(())
+    }
+
+    fn from_words(words: Vec<u64>) -> Self {
+        let mut blocks = Vec::with_capacity(words.len());
+        let mut necklaces = Vec::new();
+        let mut count = 0;
+        for (i, &word) in words.iter().enumerate() {
+            blocks.push(count);
+            count += word.count_ones();
+            let mut bits = word;
+            while bits != 0 {
+                let x = 64 * i + bits.trailing_zeros() as usize;
+                necklaces.push(T::from(x).unwrap());
+                bits &= bits - 1;
+            }
+        }
+        Self {
+            words,
+            blocks,
+            necklaces,
+        }
+    }
+
+    /// Number of necklaces of length N
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.necklaces.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.necklaces.is_empty()
+    }
+
+    /// Rank of w, which must be an N-bit word
+    #[inline]
+    pub fn rank(&self, w: T) -> T {
+        assert!(
+            N == T::zero().count_zeros() as usize || w >> N == T::zero(),
+            "word has more than {N} bits"
+        );
+        let x = w.to_usize().unwrap();
+        let (i, j) = (x / 64, x % 64);
+        // necklaces <= w in the current word
+        let low = self.words[i] & (u64::MAX >> (63 - j));
+        T::from(self.blocks[i] + low.count_ones()).unwrap() - T::one()
+    }
+
+    /// Necklace of rank r
+    #[inline]
+    pub fn unrank(&self, r: T) -> T {
+        self.necklaces[r.to_usize().unwrap()]
+    }
+
+    /// Ranks of all the necklaces of ws
+    pub fn rank_batch(&self, ws: &[T]) -> Vec<T> {
+        ws.iter().map(|&w| self.rank(w)).collect()
+    }
+
+    /// Serialize the table, the rank and unrank indexes are rebuilt when reading
+    pub fn write_to<Wr: Write>(&self, writer: &mut Wr) -> io::Result<()> {
+        writer.write_all(&(N as u64).to_le_bytes())?;
+        for word in self.words.iter() {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize a table written by `write_to`
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf)?;
+        let n = u64::from_le_bytes(buf) as usize;
+        if n != N {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("table is for N={n}, expected N={N}"),
+            ));
+        }
+        Self::check_type().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut words = vec![0u64; (1usize << N).div_ceil(64)];
+        for word in words.iter_mut() {
+            reader.read_exact(&mut buf)?;
+            *word = u64::from_le_bytes(buf);
+        }
+        Ok(Self::from_words(words))
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Count the allocations of each thread, to check that rankers reuse their workspace
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCS.with(|n| n.set(n.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
 
     #[test]
     fn test_phi() {
@@ -170,6 +635,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_num_necklaces() {
+        use crate::lyndon::necklace_n;
+        use crate::utils::all_lmers;
+        // OEIS A000031 and A001037
+        assert_eq!(
+            (1..=12).map(num_necklaces).collect::<Vec<_>>(),
+            [2, 3, 4, 6, 8, 14, 20, 36, 60, 108, 188, 352]
+        );
+        assert_eq!(
+            (1..=12).map(num_lyndon_words).collect::<Vec<_>>(),
+            [2, 1, 2, 3, 6, 9, 18, 30, 56, 99, 186, 335]
+        );
+        assert_eq!(num_necklaces(17), all_lmers::<9>().len() as u128);
+        for n in [14, 15, 16] {
+            let necklaces = (0u32..(1 << n)).filter(|&x| necklace_n(x, n) == x).count();
+            assert_eq!(num_necklaces(n), necklaces as u128);
+        }
+        assert_eq!(num_lyndon_words(127), ((1u128 << 127) - 2) / 127);
+        assert_eq!(num_necklaces(127), num_lyndon_words(127) + 2);
+        let n = 125;
+        let ranker = Ranker::<125, u128>::new();
+        assert_eq!(ranker.rank((1 << n) - 1) + 1, num_necklaces(n));
+    }
+
     #[test]
     fn test_rank_lmers() {
         use crate::utils::all_lmers;
@@ -182,4 +672,236 @@ mod tests {
             assert_eq!(ranker.rank(x), i as T);
         });
     }
+
+    #[test]
+    fn test_unrank_lmers() {
+        use crate::utils::all_lmers;
+        type T = u32;
+        const K: usize = 9;
+        const N: usize = 2 * K - 1;
+        let ranker = Ranker::<N, T>::new();
+        all_lmers::<K>().iter().enumerate().for_each(|(i, &x)| {
+            assert_eq!(ranker.unrank(i as T), x);
+            assert_eq!(ranker.unrank(ranker.rank(x)), x);
+        });
+    }
+
+    #[test]
+    fn test_unrank_composite() {
+        use crate::lyndon::necklace_n;
+        type T = u16;
+        const N: usize = 15;
+        let ranker = Ranker::<N, T>::new();
+        let mut r = 0;
+        for x in 0..(1 << N) {
+            if necklace_n(x, N) == x {
+                assert_eq!(ranker.rank(x), r);
+                assert_eq!(ranker.unrank(r), x);
+                r += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_unrank_types() {
+        use crate::utils::all_lmers;
+        const K: usize = 3;
+        const N: usize = 2 * K - 1;
+        let lmers = all_lmers::<K>();
+        let r8 = Ranker::<N, u8>::new();
+        let r16 = Ranker::<N, u16>::new();
+        let r64 = Ranker::<N, u64>::new();
+        let r128 = Ranker::<N, u128>::new();
+        lmers.iter().enumerate().for_each(|(i, &x)| {
+            assert_eq!(r8.unrank(i as u8), x as u8);
+            assert_eq!(r16.unrank(i as u16), x as u16);
+            assert_eq!(r64.unrank(i as u64), x as u64);
+            assert_eq!(r128.unrank(i as u128), x as u128);
+        });
+    }
+
+    #[test]
+    fn test_rank_wide() {
+        use crate::utils::all_lmers;
+        use crate::wide::WideUint;
+        type T = WideUint<2>;
+        const K: usize = 7;
+        const N: usize = 2 * K - 1;
+        let ranker = Ranker::<N, T>::new();
+        all_lmers::<K>().iter().enumerate().for_each(|(i, &x)| {
+            let x = T::from_u64(x as u64);
+            assert_eq!(ranker.rank(x), T::from_u64(i as u64));
+            assert_eq!(ranker.unrank(T::from_u64(i as u64)), x);
+        });
+    }
+
+    #[test]
+    fn test_unrank_long() {
+        use crate::kmer::RawKmer;
+        use crate::lyndon::Lyndon;
+        use crate::utils::random_kmers;
+        use crate::wide::WideUint;
+        type T = WideUint<3>;
+        const K: usize = 81;
+        const N: usize = 2 * K - 1;
+        let ranker = Ranker::<N, T>::new();
+        for kmer in random_kmers::<K, T, RawKmer<K, T>>(3) {
+            let lmer = kmer.lmer();
+            assert_eq!(ranker.unrank(ranker.rank(lmer)), lmer);
+        }
+    }
+
+    #[test]
+    fn test_rank_batch() {
+        use crate::kmer::RawKmer;
+        use crate::lyndon::Lyndon;
+        use crate::utils::random_kmers;
+        const K: usize = 31;
+        const N: usize = 2 * K - 1;
+        let ranker = Ranker::<N, u64>::new();
+        let lmers: Vec<_> = random_kmers::<K, u64, RawKmer<K, u64>>(100)
+            .iter()
+            .map(|kmer| kmer.lmer())
+            .collect();
+        let ranks = ranker.rank_batch(&lmers);
+        let mut ws = ranker.workspace();
+        for (&lmer, &r) in lmers.iter().zip(ranks.iter()) {
+            assert_eq!(ranker.rank(lmer), r);
+            assert_eq!(ranker.rank_with(lmer, &mut ws), r);
+            assert_eq!(ranker.unrank_with(r, &mut ws), lmer);
+        }
+        // the ranker is shared between threads, each with its own workspace
+        std::thread::scope(|scope| {
+            for chunk in lmers.chunks(25) {
+                let ranker = &ranker;
+                scope.spawn(move || {
+                    let mut ws = ranker.workspace();
+                    for &lmer in chunk {
+                        assert_eq!(ranker.unrank(ranker.rank_with(lmer, &mut ws)), lmer);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_no_alloc() {
+        use crate::kmer::{Kmer, RawKmer};
+        use crate::lyndon::Lyndon;
+        const K: usize = 61;
+        const N: usize = 2 * K - 1;
+        let ranker = Ranker::<N, u128>::new();
+        let kmers: Vec<_> = (0..100u128)
+            .map(|i| RawKmer::<K, u128>::from_int(i.wrapping_mul(0x9e3779b97f4a7c15) >> 6))
+            .collect();
+        let before = ALLOCS.with(Cell::get);
+        for &kmer in kmers.iter() {
+            let lmer = kmer.lmer();
+            let r = ranker.rank(lmer);
+            assert_eq!(ranker.unrank(r), lmer);
+            let code = kmer.dense_code(&ranker);
+            assert_eq!(RawKmer::from_dense_code(code, &ranker), kmer.canonical());
+        }
+        assert_eq!(ALLOCS.with(Cell::get), before);
+    }
+
+    #[test]
+    fn test_dyn_rank() {
+        const N: usize = 15;
+        let ranker = Ranker::<N, u32>::new();
+        let dyn_ranker = DynRanker::<u32>::new(N);
+        for x in (0..(1 << N)).step_by(7) {
+            assert_eq!(dyn_ranker.rank(x), ranker.rank(x));
+        }
+        for r in 0..100 {
+            assert_eq!(dyn_ranker.unrank(r), ranker.unrank(r));
+        }
+    }
+
+    #[test]
+    fn test_try_rank() {
+        use crate::lyndon::necklace_n;
+        const N: usize = 13;
+        let ranker = Ranker::<N, u16>::new();
+        for x in 0..(1 << N) {
+            if necklace_n(x, N) == x {
+                assert_eq!(ranker.try_rank(x), Ok(ranker.rank(x)));
+            } else {
+                assert_eq!(ranker.try_rank(x), Err(RankError::NotNecklace));
+            }
+        }
+        assert_eq!(ranker.try_rank(1 << N), Err(RankError::NotNecklace));
+        assert!(Ranker::<7, u8>::try_new().is_ok());
+        assert_eq!(Ranker::<8, u8>::try_new().err(), Some(RankError::Overflow));
+        assert_eq!(
+            DynRanker::<u64>::try_new(0).err(),
+            Some(RankError::Overflow)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ranker_overflow() {
+        Ranker::<17, u16>::new();
+    }
+
+    #[test]
+    fn test_dense_code() {
+        use crate::lyndon::necklace_index_n;
+        const N: usize = 13;
+        let ranker = Ranker::<N, u16>::new();
+        let mut codes: Vec<_> = (0..(1 << N))
+            .map(|x| {
+                let (lmer, idx) = necklace_index_n(x, N);
+                let code = ranker.encode(lmer, idx);
+                assert_eq!(ranker.decode(code), (lmer, idx));
+                (code, lmer)
+            })
+            .collect();
+        codes.sort();
+        assert!(codes
+            .iter()
+            .enumerate()
+            .all(|(i, &(code, _))| code == i as u16));
+        assert!(codes.windows(2).all(|w| w[0].1 <= w[1].1));
+    }
+
+    #[test]
+    fn test_table_ranker() {
+        const N: usize = 15;
+        let ranker = Ranker::<N, u32>::new();
+        let table = TableRanker::<N, u32>::new();
+        for x in 0..(1 << N) {
+            assert_eq!(table.rank(x), ranker.rank(x));
+        }
+        for r in 0..table.len() as u32 {
+            assert_eq!(table.unrank(r), ranker.unrank(r));
+        }
+    }
+
+    #[test]
+    fn test_table_serialize() {
+        const N: usize = 17;
+        let table = TableRanker::<N, u32>::new();
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+        let loaded = TableRanker::<N, u32>::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.len(), table.len());
+        assert_eq!(loaded.necklaces, table.necklaces);
+        assert_eq!(loaded.blocks, table.blocks);
+        assert!(TableRanker::<15, u32>::read_from(&mut bytes.as_slice()).is_err());
+        assert!(TableRanker::<N, u16>::read_from(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_table_overflow() {
+        assert!(TableRanker::<15, u8>::try_new().is_err());
+        assert!(TableRanker::<8, u8>::try_new().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "word has more than 10 bits")]
+    fn test_table_rank_too_long() {
+        TableRanker::<10, u32>::new().rank(1 << 10);
+    }
 }
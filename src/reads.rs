@@ -1,3 +1,4 @@
+use crate::kmer::{Base, Kmer, SoftMask};
 use seq_io::fasta::{Reader, RefRecord};
 use seq_io::parallel::read_process_fasta_records;
 use seq_io::BaseRecord;
@@ -17,9 +18,33 @@ impl Fasta {
     }
 }
 
-pub trait ReadProcess {
+pub trait ReadProcess: Sized {
     fn process<F: FnMut(Iter<u8>)>(self, f: F);
     fn parallel_process<F: Send + Sync + Fn(Iter<u8>)>(self, threads: u32, queue_len: usize, f: F);
+
+    /// Process the k-mers of each read, skipping those containing invalid
+    /// nucleotides or lowercase ones when `mask` is `SoftMask::Mask`
+    fn process_kmers<const K: usize, T: Base, KT: Kmer<K, T>, F: FnMut(KT)>(
+        self,
+        mask: SoftMask,
+        mut f: F,
+    ) {
+        self.process(|nucs| KT::iter_split_from_nucs_with(nucs, mask).for_each(&mut f));
+    }
+
+    fn parallel_process_kmers<const K: usize, T: Base, KT: Kmer<K, T>, F>(
+        self,
+        threads: u32,
+        queue_len: usize,
+        mask: SoftMask,
+        f: F,
+    ) where
+        F: Send + Sync + Fn(KT),
+    {
+        self.parallel_process(threads, queue_len, |nucs| {
+            KT::iter_split_from_nucs_with(nucs, mask).for_each(&f)
+        });
+    }
 }
 
 impl ReadProcess for Fasta {
@@ -43,3 +68,35 @@ impl ReadProcess for Fasta {
         .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::RawKmer;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_process_kmers() {
+        let path = std::env::temp_dir().join(format!("lmer_reads_{}.fa", std::process::id()));
+        std::fs::write(&path, b">r1\nACGTacgtACG\n>r2\nACNTTTG\n").unwrap();
+        let mut kmers = Vec::new();
+        Fasta::from_file(&path).process_kmers(SoftMask::Mask, |kmer: RawKmer<3, u8>| {
+            kmers.push(kmer.to_nucs())
+        });
+        assert_eq!(kmers, [*b"ACG", *b"CGT", *b"ACG", *b"TTT", *b"TTG"]);
+        let mut count = 0;
+        Fasta::from_file(&path).process_kmers(SoftMask::Unmask, |_: RawKmer<3, u8>| count += 1);
+        assert_eq!(count, 9 + 2);
+        let kmers = Mutex::new(Vec::new());
+        Fasta::from_file(&path).parallel_process_kmers(
+            2,
+            4,
+            SoftMask::Mask,
+            |kmer: RawKmer<3, u8>| kmers.lock().unwrap().push(kmer.to_nucs()),
+        );
+        let mut kmers = kmers.into_inner().unwrap();
+        kmers.sort();
+        assert_eq!(kmers, [*b"ACG", *b"ACG", *b"CGT", *b"TTG", *b"TTT"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,17 +1,26 @@
 #![allow(dead_code)]
-use crate::kmer::{Base, Kmer, RawKmer};
-use crate::lyndon::Lyndon;
+use crate::canonical::canon_bits;
+use crate::kmer::{Base, Kmer};
+use crate::lyndon::necklaces_n;
 use core::fmt::Binary;
+use num_traits::int::PrimInt;
 use rand::Rng;
 use std::collections::BTreeSet;
 
-pub fn all_lmers<const K: usize>() -> BTreeSet<u32> {
-    let mut lmers = BTreeSet::new();
-    for i in 0..=RawKmer::<K, u32>::MASK {
-        let kmer = RawKmer::<K, u32>::from_int(i);
-        lmers.insert(kmer.lmer());
+/// Integer with the `n` lowest bits set
+#[inline]
+pub fn low_mask<T: PrimInt>(n: usize) -> T {
+    let bits = T::zero().count_zeros() as usize;
+    if n == 0 {
+        T::zero()
+    } else {
+        T::max_value() >> (bits - n)
     }
-    lmers
+}
+
+/// All necklaces of length `canon_bits(K)`, which include the lmers of k-mers
+pub fn all_lmers<const K: usize>() -> BTreeSet<u32> {
+    necklaces_n(canon_bits(K)).collect()
 }
 
 pub fn random_kmers<const K: usize, T: Base, KT: Kmer<K, T>>(n: usize) -> Vec<KT> {
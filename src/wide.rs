@@ -0,0 +1,573 @@
+use core::fmt::{self, Binary, Display};
+use core::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub};
+use num_traits::{
+    AsPrimitive, Bounded, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Num, NumCast, One,
+    PrimInt, Saturating, ToPrimitive, Zero,
+};
+
+/// Unsigned integer stored in `W` 64-bit words, most significant word first,
+/// so that the derived ordering is the numerical one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WideUint<const W: usize>(pub [u64; W]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseWideUintError;
+
+impl<const W: usize> WideUint<W> {
+    pub const BITS: usize = 64 * W;
+    pub const ZERO: Self = Self([0; W]);
+    pub const MAX: Self = Self([u64::MAX; W]);
+
+    pub const fn from_u64(x: u64) -> Self {
+        let mut words = [0; W];
+        words[W - 1] = x;
+        Self(words)
+    }
+
+    /// Integer with the `n` lowest bits set
+    pub const fn low_mask(n: usize) -> Self {
+        let mut words = [0; W];
+        let mut i = 0;
+        while i < W {
+            let offset = 64 * (W - 1 - i);
+            words[i] = if n >= offset + 64 {
+                u64::MAX
+            } else if n > offset {
+                (1 << (n - offset)) - 1
+            } else {
+                0
+            };
+            i += 1;
+        }
+        Self(words)
+    }
+
+    pub fn from_u128(x: u128) -> Option<Self> {
+        let hi = (x >> 64) as u64;
+        let mut words = [0; W];
+        words[W - 1] = x as u64;
+        if W > 1 {
+            words[W - 2] = hi;
+        } else if hi != 0 {
+            return None;
+        }
+        Some(Self(words))
+    }
+
+    #[inline]
+    fn is_zero_const(&self) -> bool {
+        self.0.iter().all(|&w| w == 0)
+    }
+
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let mut res = [0; W];
+        let mut carry = false;
+        for i in (0..W).rev() {
+            let (s, c1) = self.0[i].overflowing_add(rhs.0[i]);
+            let (s, c2) = s.overflowing_add(carry as u64);
+            res[i] = s;
+            carry = c1 || c2;
+        }
+        (Self(res), carry)
+    }
+
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let mut res = [0; W];
+        let mut borrow = false;
+        for i in (0..W).rev() {
+            let (s, b1) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (s, b2) = s.overflowing_sub(borrow as u64);
+            res[i] = s;
+            borrow = b1 || b2;
+        }
+        (Self(res), borrow)
+    }
+
+    pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        let mut res = [0; W];
+        let mut overflow = false;
+        // schoolbook multiplication on little-endian word indices
+        for i in 0..W {
+            let a = self.0[W - 1 - i] as u128;
+            if a == 0 {
+                continue;
+            }
+            let mut carry = 0u128;
+            for j in 0..W {
+                let b = rhs.0[W - 1 - j] as u128;
+                if i + j >= W {
+                    overflow |= a * b != 0;
+                    continue;
+                }
+                let cur = res[W - 1 - i - j] as u128 + a * b + carry;
+                res[W - 1 - i - j] = cur as u64;
+                carry = cur >> 64;
+            }
+            overflow |= carry != 0;
+        }
+        (Self(res), overflow)
+    }
+
+    /// Quotient and remainder of the euclidean division by `rhs`
+    #[allow(clippy::needless_range_loop)]
+    pub fn div_rem(self, rhs: Self) -> (Self, Self) {
+        assert!(!rhs.is_zero_const(), "attempt to divide by zero");
+        if rhs.0[..W - 1].iter().all(|&w| w == 0) {
+            // single word divisor
+            let d = rhs.0[W - 1] as u128;
+            let mut quot = [0; W];
+            let mut rem = 0u128;
+            for i in 0..W {
+                let cur = (rem << 64) | self.0[i] as u128;
+                quot[i] = (cur / d) as u64;
+                rem = cur % d;
+            }
+            return (Self(quot), Self::from_u64(rem as u64));
+        }
+        let mut quot = Self::ZERO;
+        let mut rem = Self::ZERO;
+        for i in (0..Self::BITS - self.leading_zeros() as usize).rev() {
+            rem = (rem << 1) | ((self >> i) & Self::one());
+            if rem >= rhs {
+                rem = rem.overflowing_sub(rhs).0;
+                quot = quot | (Self::one() << i);
+            }
+        }
+        (quot, rem)
+    }
+}
+
+impl<const W: usize> Default for WideUint<W> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<const W: usize> Not for WideUint<W> {
+    type Output = Self;
+    #[inline]
+    fn not(self) -> Self {
+        Self(self.0.map(|w| !w))
+    }
+}
+
+macro_rules! impl_bitwise {
+($($Trait:ident, $f:ident, $op:tt);+) => {$(
+    impl<const W: usize> $Trait for WideUint<W> {
+        type Output = Self;
+        #[inline]
+        fn $f(self, rhs: Self) -> Self {
+            let mut res = self.0;
+            for (r, w) in res.iter_mut().zip(rhs.0) {
+                *r $op w;
+            }
+            Self(res)
+        }
+    }
+)*}}
+
+impl_bitwise!(BitAnd, bitand, &=; BitOr, bitor, |=; BitXor, bitxor, ^=);
+
+impl<const W: usize> Shl<usize> for WideUint<W> {
+    type Output = Self;
+    #[allow(clippy::needless_range_loop)]
+    fn shl(self, n: usize) -> Self {
+        debug_assert!(n < Self::BITS, "attempt to shift left with overflow");
+        let (ws, bs) = (n / 64, n % 64);
+        let mut res = [0; W];
+        for i in 0..W.saturating_sub(ws) {
+            res[i] = self.0[i + ws] << bs;
+            if bs > 0 && i + ws + 1 < W {
+                res[i] |= self.0[i + ws + 1] >> (64 - bs);
+            }
+        }
+        Self(res)
+    }
+}
+
+impl<const W: usize> Shr<usize> for WideUint<W> {
+    type Output = Self;
+    #[allow(clippy::needless_range_loop)]
+    fn shr(self, n: usize) -> Self {
+        debug_assert!(n < Self::BITS, "attempt to shift right with overflow");
+        let (ws, bs) = (n / 64, n % 64);
+        let mut res = [0; W];
+        for i in ws..W {
+            res[i] = self.0[i - ws] >> bs;
+            if bs > 0 && i > ws {
+                res[i] |= self.0[i - ws - 1] << (64 - bs);
+            }
+        }
+        Self(res)
+    }
+}
+
+impl<const W: usize> Add for WideUint<W> {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        let (res, overflow) = self.overflowing_add(rhs);
+        debug_assert!(!overflow, "attempt to add with overflow");
+        res
+    }
+}
+
+impl<const W: usize> Sub for WideUint<W> {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        let (res, overflow) = self.overflowing_sub(rhs);
+        debug_assert!(!overflow, "attempt to subtract with overflow");
+        res
+    }
+}
+
+impl<const W: usize> Mul for WideUint<W> {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        let (res, overflow) = self.overflowing_mul(rhs);
+        debug_assert!(!overflow, "attempt to multiply with overflow");
+        res
+    }
+}
+
+impl<const W: usize> Div for WideUint<W> {
+    type Output = Self;
+    #[inline]
+    fn div(self, rhs: Self) -> Self {
+        self.div_rem(rhs).0
+    }
+}
+
+impl<const W: usize> Rem for WideUint<W> {
+    type Output = Self;
+    #[inline]
+    fn rem(self, rhs: Self) -> Self {
+        self.div_rem(rhs).1
+    }
+}
+
+impl<const W: usize> Zero for WideUint<W> {
+    #[inline]
+    fn zero() -> Self {
+        Self::ZERO
+    }
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.is_zero_const()
+    }
+}
+
+impl<const W: usize> One for WideUint<W> {
+    #[inline]
+    fn one() -> Self {
+        Self::from_u64(1)
+    }
+}
+
+impl<const W: usize> Num for WideUint<W> {
+    type FromStrRadixErr = ParseWideUintError;
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        if s.is_empty() {
+            return Err(ParseWideUintError);
+        }
+        let radix_w = Self::from_u64(radix as u64);
+        s.chars().try_fold(Self::ZERO, |acc, c| {
+            let digit = c.to_digit(radix).ok_or(ParseWideUintError)?;
+            acc.checked_mul(&radix_w)
+                .and_then(|x| x.checked_add(&Self::from_u64(digit as u64)))
+                .ok_or(ParseWideUintError)
+        })
+    }
+}
+
+impl<const W: usize> Bounded for WideUint<W> {
+    #[inline]
+    fn min_value() -> Self {
+        Self::ZERO
+    }
+    #[inline]
+    fn max_value() -> Self {
+        Self::MAX
+    }
+}
+
+impl<const W: usize> ToPrimitive for WideUint<W> {
+    fn to_i64(&self) -> Option<i64> {
+        self.to_u64().and_then(|x| x.to_i64())
+    }
+    fn to_u64(&self) -> Option<u64> {
+        if self.0[..W - 1].iter().all(|&w| w == 0) {
+            Some(self.0[W - 1])
+        } else {
+            None
+        }
+    }
+    fn to_u128(&self) -> Option<u128> {
+        if W == 1 {
+            return Some(self.0[0] as u128);
+        }
+        if self.0[..W - 2].iter().all(|&w| w == 0) {
+            Some(((self.0[W - 2] as u128) << 64) | self.0[W - 1] as u128)
+        } else {
+            None
+        }
+    }
+}
+
+impl<const W: usize> NumCast for WideUint<W> {
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        n.to_u128().and_then(Self::from_u128)
+    }
+}
+
+/// Truncating cast to the lowest word, like `as usize` on primitive integers
+impl<const W: usize> AsPrimitive<usize> for WideUint<W> {
+    #[inline]
+    fn as_(self) -> usize {
+        self.0[W - 1] as usize
+    }
+}
+
+impl<const W: usize> CheckedAdd for WideUint<W> {
+    fn checked_add(&self, v: &Self) -> Option<Self> {
+        match self.overflowing_add(*v) {
+            (res, false) => Some(res),
+            _ => None,
+        }
+    }
+}
+
+impl<const W: usize> CheckedSub for WideUint<W> {
+    fn checked_sub(&self, v: &Self) -> Option<Self> {
+        match self.overflowing_sub(*v) {
+            (res, false) => Some(res),
+            _ => None,
+        }
+    }
+}
+
+impl<const W: usize> CheckedMul for WideUint<W> {
+    fn checked_mul(&self, v: &Self) -> Option<Self> {
+        match self.overflowing_mul(*v) {
+            (res, false) => Some(res),
+            _ => None,
+        }
+    }
+}
+
+impl<const W: usize> CheckedDiv for WideUint<W> {
+    fn checked_div(&self, v: &Self) -> Option<Self> {
+        if v.is_zero() {
+            None
+        } else {
+            Some(*self / *v)
+        }
+    }
+}
+
+impl<const W: usize> Saturating for WideUint<W> {
+    fn saturating_add(self, v: Self) -> Self {
+        self.checked_add(&v).unwrap_or(Self::MAX)
+    }
+    fn saturating_sub(self, v: Self) -> Self {
+        self.checked_sub(&v).unwrap_or(Self::ZERO)
+    }
+}
+
+impl<const W: usize> PrimInt for WideUint<W> {
+    fn count_ones(self) -> u32 {
+        self.0.iter().map(|w| w.count_ones()).sum()
+    }
+    fn count_zeros(self) -> u32 {
+        self.0.iter().map(|w| w.count_zeros()).sum()
+    }
+    fn leading_zeros(self) -> u32 {
+        let mut res = 0;
+        for w in self.0 {
+            res += w.leading_zeros();
+            if w != 0 {
+                break;
+            }
+        }
+        res
+    }
+    fn trailing_zeros(self) -> u32 {
+        let mut res = 0;
+        for w in self.0.into_iter().rev() {
+            res += w.trailing_zeros();
+            if w != 0 {
+                break;
+            }
+        }
+        res
+    }
+    fn rotate_left(self, n: u32) -> Self {
+        let n = n as usize % Self::BITS;
+        if n == 0 {
+            self
+        } else {
+            (self << n) | (self >> (Self::BITS - n))
+        }
+    }
+    fn rotate_right(self, n: u32) -> Self {
+        let n = n as usize % Self::BITS;
+        if n == 0 {
+            self
+        } else {
+            (self >> n) | (self << (Self::BITS - n))
+        }
+    }
+    fn signed_shl(self, n: u32) -> Self {
+        self << n as usize
+    }
+    fn signed_shr(self, n: u32) -> Self {
+        let n = n as usize;
+        if self.0[0] >> 63 == 0 || n == 0 {
+            self >> n
+        } else {
+            (self >> n) | !(Self::MAX >> n)
+        }
+    }
+    fn unsigned_shl(self, n: u32) -> Self {
+        self << n as usize
+    }
+    fn unsigned_shr(self, n: u32) -> Self {
+        self >> n as usize
+    }
+    fn swap_bytes(self) -> Self {
+        let mut res = self.0.map(|w| w.swap_bytes());
+        res.reverse();
+        Self(res)
+    }
+    fn from_be(x: Self) -> Self {
+        x.to_be()
+    }
+    fn from_le(x: Self) -> Self {
+        x.to_le()
+    }
+    fn to_be(self) -> Self {
+        if cfg!(target_endian = "big") {
+            self
+        } else {
+            self.swap_bytes()
+        }
+    }
+    fn to_le(self) -> Self {
+        if cfg!(target_endian = "little") {
+            self
+        } else {
+            self.swap_bytes()
+        }
+    }
+    fn pow(self, exp: u32) -> Self {
+        let mut res = Self::one();
+        let mut base = self;
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = res * base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base * base;
+            }
+        }
+        res
+    }
+}
+
+impl<const W: usize> Display for WideUint<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let chunk = Self::from_u64(CHUNK);
+        let mut chunks = Vec::new();
+        let mut x = *self;
+        loop {
+            let (q, r) = x.div_rem(chunk);
+            chunks.push(r.0[W - 1]);
+            x = q;
+            if x.is_zero() {
+                break;
+            }
+        }
+        let mut s = chunks.pop().unwrap().to_string();
+        for c in chunks.into_iter().rev() {
+            s.push_str(&format!("{:019}", c));
+        }
+        f.pad_integral(true, "", &s)
+    }
+}
+
+impl<const W: usize> Binary for WideUint<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words = self.0.iter().skip_while(|&&w| w == 0);
+        let s = match words.next() {
+            None => "0".to_string(),
+            Some(w) => words.fold(format!("{:b}", w), |s, w| s + &format!("{:064b}", w)),
+        };
+        f.pad_integral(true, "0b", &s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    type U2 = WideUint<2>;
+
+    fn wide(x: u128) -> U2 {
+        U2::from_u128(x).unwrap()
+    }
+
+    #[test]
+    fn test_ops_u128() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..10_000 {
+            let a: u128 = rng.gen::<u128>() >> rng.gen_range(0..128);
+            let b: u128 = rng.gen::<u128>() >> rng.gen_range(0..128);
+            let n = rng.gen_range(0..128);
+            assert_eq!(wide(a) & wide(b), wide(a & b));
+            assert_eq!(wide(a) | wide(b), wide(a | b));
+            assert_eq!(wide(a) ^ wide(b), wide(a ^ b));
+            assert_eq!(!wide(a), wide(!a));
+            assert_eq!(wide(a) << n, wide(a << n));
+            assert_eq!(wide(a) >> n, wide(a >> n));
+            assert_eq!(wide(a).overflowing_add(wide(b)).0, wide(a.wrapping_add(b)));
+            assert_eq!(wide(a).overflowing_sub(wide(b)).0, wide(a.wrapping_sub(b)));
+            assert_eq!(wide(a).overflowing_mul(wide(b)).0, wide(a.wrapping_mul(b)));
+            assert_eq!(wide(a).cmp(&wide(b)), a.cmp(&b));
+            assert_eq!(AsPrimitive::<usize>::as_(wide(a)), a as usize);
+            assert_eq!(wide(a).count_ones(), a.count_ones());
+            assert_eq!(wide(a).leading_zeros(), a.leading_zeros());
+            assert_eq!(wide(a).trailing_zeros(), a.trailing_zeros());
+            assert_eq!(wide(a).swap_bytes(), wide(a.swap_bytes()));
+            if b != 0 {
+                assert_eq!(wide(a).div_rem(wide(b)), (wide(a / b), wide(a % b)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_low_mask() {
+        for n in 0..=128 {
+            let mask = if n == 128 { u128::MAX } else { (1 << n) - 1 };
+            assert_eq!(U2::low_mask(n), wide(mask));
+        }
+    }
+
+    #[test]
+    fn test_fmt() {
+        let x = u128::MAX / 3;
+        assert_eq!(format!("{}", wide(x)), format!("{}", x));
+        assert_eq!(format!("{:b}", wide(x)), format!("{:b}", x));
+        assert_eq!(format!("{}", U2::ZERO), "0");
+        assert_eq!(
+            format!("{}", WideUint::<3>::one() << 130),
+            "1361129467683753853853498429727072845824"
+        );
+        assert_eq!(U2::from_str_radix(&x.to_string(), 10), Ok(wide(x)));
+    }
+}